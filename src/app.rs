@@ -1,89 +1,370 @@
 use std::{
-    fmt::Display,
+    cmp::Ordering,
+    collections::BTreeSet,
     io::{self},
-    str::FromStr,
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::{Duration, Instant},
 };
 
 use log::info;
+use rand::{rngs::StdRng, SeedableRng};
 use ratatui::{
     buffer::Buffer,
-    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    crossterm::event::{
+        self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEvent,
+        MouseEventKind,
+    },
     layout::Rect,
     prelude::*,
-    style::Stylize,
+    style::{Styled, Stylize},
     symbols::border,
     text::Line,
-    widgets::{Block, Borders, List, ListItem, Paragraph, Widget},
+    widgets::{
+        Block, Borders, Clear, List, ListItem, ListState, Paragraph, Scrollbar,
+        ScrollbarOrientation, ScrollbarState, Widget, Wrap,
+    },
     DefaultTerminal,
 };
 use tui_textarea::{CursorMove, TextArea};
 
+use combat_tracker::{
+    bestiary::{Bestiary, Template},
+    checks::{Check, Degree, SaveKind},
+    conditions::{self, ConditionKind},
+    damage::{Adjustments, Damage, DamageExpression, PersistentDamage},
+    dice::Expression,
+    encounter::PersistentTick,
+    history::{self, History},
+    import,
+    save::{SaveFile, SAVE_VERSION},
+    Creature, Encounter, HealthShift, Side,
+};
+
+use crate::{
+    autosave::Autosave,
+    keymap::{Action, Context, Keymap},
+    theme::Theme,
+};
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 enum Mode {
+    Recover,
     Help,
     Normal,
     Meta(usize),
+    SaveAs(String),
+    // Path of a Pathbuilder or Foundry export being typed
+    Import(String),
     Rename(String),
-    SetHealth(i32),
-    SetInitiative(i32),
-    HealthShift,
+    // The number inputs take dice expressions, typed into App::input
+    SetHealth,
+    SetMaxHealth,
+    SetTempHealth,
+    SetInitiative,
+    SetInitiativeModifier,
+    HealthShift(HealthShift),
+    // Armor class, saves and perception as plain numbers, also typed into App::input
+    SetDefenses,
+    // Immunities, weaknesses and resistances written out, typed into App::input
+    SetAdjustments,
+    // How many copies of the hovered creature to add
+    Copies,
     EditNotes,
     Sort,
+    // Index into ConditionKind::ALL
+    Conditions(usize),
+    // Popup listing what happened when the turn passed
+    TurnSummary,
+    // Picker for adding a creature from the bestiary, selection indexes the matches
+    Bestiary {
+        query: String,
+        selection: usize,
+    },
+    // Damage that allows a basic save, typed into App::input
+    BasicSave,
+    // Damage rolled, waiting for which save and the DC, typed into App::input
+    SaveDc {
+        damage: Damage,
+        targets: Vec<usize>,
+        save: SaveKind,
+    },
+    // Damage rolled against the targets, waiting for how each saved.
+    // The save and DC are there when the saves were rolled.
+    SaveOutcomes {
+        damage: Damage,
+        save: Option<(SaveKind, i32)>,
+        outcomes: Vec<SaveOutcome>,
+        selection: usize,
+    },
+    // A dying character's turn just started, waiting to confirm how the recovery check went
+    RecoveryCheck {
+        creature: usize,
+        check: Check,
+        degree: Degree,
+    },
+    // The turn of a creature with persistent damage just ended, waiting to apply it
+    PersistentDamage {
+        creature: usize,
+        ticks: Vec<PersistentTick>,
+        selection: usize,
+    },
 }
 impl Mode {
-    fn get_instructions(&self) -> Line {
+    fn get_instructions(&self, keymap: &Keymap, theme: &Theme) -> Line<'static> {
         match self {
             Mode::Help => panic!("Should not ask for instructions in help mode"),
+            Mode::Recover => Line::from(vec![
+                " Restore: ".set_style(theme.label),
+                keymap
+                    .describe(&[Action::RestoreSession])
+                    .set_style(theme.key),
+                " Discard: ".set_style(theme.label),
+                format!("{} ", keymap.describe(&[Action::DiscardSession])).set_style(theme.key),
+            ]),
             Mode::Meta(_) => Line::from(vec![
-                " Back to normal mode: ".white(),
-                "Esc".blue().bold(),
-                " Navigate: ".white(),
-                "j/k".blue().bold(),
-                " Select: ".white(),
-                "Enter ".blue().bold(),
+                " Back to normal mode: ".set_style(theme.label),
+                "Esc".set_style(theme.key),
+                " Navigate: ".set_style(theme.label),
+                keymap
+                    .describe(&[Action::SelectNext, Action::SelectPrevious])
+                    .set_style(theme.key),
+                " Select: ".set_style(theme.label),
+                "Enter ".set_style(theme.key),
             ]),
             Mode::Normal => Line::from(vec![
-                " Exit: ".white(),
-                "Esc".blue().bold(),
-                " Help: ".white(),
-                "? ".blue().bold(),
+                " Exit: ".set_style(theme.label),
+                keymap.describe(&[Action::Menu]).set_style(theme.key),
+                " Help: ".set_style(theme.label),
+                format!("{} ", keymap.describe(&[Action::Help])).set_style(theme.key),
+            ]),
+            Mode::Rename(_)
+            | Mode::SaveAs(_)
+            | Mode::Import(_)
+            | Mode::SetHealth
+            | Mode::SetMaxHealth
+            | Mode::SetTempHealth
+            | Mode::SetInitiative
+            | Mode::SetInitiativeModifier
+            | Mode::HealthShift(_)
+            | Mode::SetDefenses
+            | Mode::SetAdjustments
+            | Mode::Copies
+            | Mode::BasicSave => Line::from(vec![
+                " Confirm: ".set_style(theme.label),
+                "Enter".set_style(theme.key),
+                ", Cancel: ".set_style(theme.label),
+                "Esc ".set_style(theme.key),
             ]),
-            Mode::Rename(_) | Mode::SetHealth(_) | Mode::SetInitiative(_) | Mode::HealthShift => {
-                Line::from(vec![
-                    " Confirm: ".white(),
-                    "Enter".blue().bold(),
-                    ", Cancel: ".white(),
-                    "Esc ".blue().bold(),
-                ])
-            }
             Mode::Sort => Line::from(vec![
-                " Press letter to determine order, shift reverses: (".white(),
-                "I".blue().bold(),
-                ")nitiative, (".white(),
-                "H".blue().bold(),
-                ")ealth, (".white(),
-                "N".blue().bold(),
-                ")ame or ".white(),
-                "Esc".blue().bold(),
-                "to cancel".white(),
+                " Sort by initiative: ".set_style(theme.label),
+                keymap
+                    .describe(&[Action::SortByInitiative, Action::SortByInitiativeReversed])
+                    .set_style(theme.key),
+                " Health: ".set_style(theme.label),
+                keymap
+                    .describe(&[Action::SortByHealth, Action::SortByHealthReversed])
+                    .set_style(theme.key),
+                " Name: ".set_style(theme.label),
+                keymap
+                    .describe(&[Action::SortByName, Action::SortByNameReversed])
+                    .set_style(theme.key),
+                " Cancel: ".set_style(theme.label),
+                "Esc ".set_style(theme.key),
+            ]),
+            Mode::Conditions(_) => Line::from(vec![
+                " Navigate: ".set_style(theme.label),
+                keymap
+                    .describe(&[Action::SelectNext, Action::SelectPrevious])
+                    .set_style(theme.key),
+                " Add/increase: ".set_style(theme.label),
+                keymap
+                    .describe(&[Action::IncreaseCondition])
+                    .set_style(theme.key),
+                " Reduce/remove: ".set_style(theme.label),
+                keymap
+                    .describe(&[Action::DecreaseCondition])
+                    .set_style(theme.key),
+                " Cycle end of turn rule: ".set_style(theme.label),
+                keymap
+                    .describe(&[Action::CycleConditionRule])
+                    .set_style(theme.key),
+                " Back: ".set_style(theme.label),
+                "Esc ".set_style(theme.key),
+            ]),
+            Mode::TurnSummary => Line::from(vec![
+                " Dismiss: ".set_style(theme.label),
+                "Enter ".set_style(theme.key),
+            ]),
+            Mode::SaveDc { .. } => Line::from(vec![
+                " Save: ".set_style(theme.label),
+                keymap
+                    .describe(&[
+                        Action::Fortitude,
+                        Action::Reflex,
+                        Action::Will,
+                        Action::NextSave,
+                    ])
+                    .set_style(theme.key),
+                " Roll, or pick by hand without a DC: ".set_style(theme.label),
+                "Enter".set_style(theme.key),
+                " Cancel: ".set_style(theme.label),
+                "Esc ".set_style(theme.key),
+            ]),
+            Mode::SaveOutcomes { .. } => Line::from(vec![
+                " Navigate: ".set_style(theme.label),
+                keymap
+                    .describe(&[Action::SelectNext, Action::SelectPrevious])
+                    .set_style(theme.key),
+                " Outcome: ".set_style(theme.label),
+                outcome_keys(keymap).set_style(theme.key),
+                " Apply: ".set_style(theme.label),
+                "Enter".set_style(theme.key),
+                " Cancel: ".set_style(theme.label),
+                "Esc ".set_style(theme.key),
+            ]),
+            Mode::RecoveryCheck { .. } => Line::from(vec![
+                " Outcome: ".set_style(theme.label),
+                outcome_keys(keymap).set_style(theme.key),
+                " Apply: ".set_style(theme.label),
+                "Enter".set_style(theme.key),
+                " Skip: ".set_style(theme.label),
+                "Esc ".set_style(theme.key),
+            ]),
+            Mode::PersistentDamage { .. } => Line::from(vec![
+                " Navigate: ".set_style(theme.label),
+                keymap
+                    .describe(&[Action::SelectNext, Action::SelectPrevious])
+                    .set_style(theme.key),
+                " Flat check: ".set_style(theme.label),
+                keymap
+                    .describe(&[Action::WorseOutcome, Action::BetterOutcome])
+                    .set_style(theme.key),
+                " Apply: ".set_style(theme.label),
+                "Enter".set_style(theme.key),
+                " Skip: ".set_style(theme.label),
+                "Esc ".set_style(theme.key),
+            ]),
+            Mode::Bestiary { .. } => Line::from(vec![
+                " Type to search, Pick: ".set_style(theme.label),
+                "Up/Down".set_style(theme.key),
+                " Add: ".set_style(theme.label),
+                "Enter".set_style(theme.key),
+                " Cancel: ".set_style(theme.label),
+                "Esc ".set_style(theme.key),
             ]),
             Mode::EditNotes => Line::from(vec![
-                " Confirm: ".white(),
-                "Enter".blue().bold(),
-                " (use alt to break lines), Cancel: ".white(),
-                "Esc ".blue().bold(),
+                " Confirm: ".set_style(theme.label),
+                "Enter".set_style(theme.key),
+                " (use alt to break lines), Cancel: ".set_style(theme.label),
+                "Esc ".set_style(theme.key),
             ]),
         }
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MetaOption {
+    Return,
+    Save,
+    SaveAs,
+    Import,
+    TieBreak,
+    Numbering,
+    RerollHealth,
+    RerollInitiative,
+    RemoveDefeated,
+    Quit,
+}
+impl MetaOption {
+    const ALL: [MetaOption; 10] = [
+        MetaOption::Return,
+        MetaOption::Save,
+        MetaOption::SaveAs,
+        MetaOption::Import,
+        MetaOption::TieBreak,
+        MetaOption::Numbering,
+        MetaOption::RerollHealth,
+        MetaOption::RerollInitiative,
+        MetaOption::RemoveDefeated,
+        MetaOption::Quit,
+    ];
+
+    fn index(&self) -> usize {
+        MetaOption::ALL
+            .iter()
+            .position(|option| option == self)
+            .unwrap()
+    }
+
+    fn label(&self, app: &App) -> String {
+        match self {
+            MetaOption::Return => "Return to normal mode".into(),
+            MetaOption::Save => "Save".into(),
+            MetaOption::SaveAs => "Save as".into(),
+            MetaOption::Import => "Import from Pathbuilder or Foundry".into(),
+            MetaOption::TieBreak => {
+                format!("Initiative ties: {}", app.encounter.tie_break.describe())
+            }
+            MetaOption::Numbering => {
+                format!(
+                    "Copies are named: {}",
+                    app.encounter.copies.numbering.describe()
+                )
+            }
+            MetaOption::RerollHealth => format!(
+                "Copies reroll max health dice: {}",
+                yes_no(app.encounter.copies.reroll_health)
+            ),
+            MetaOption::RerollInitiative => format!(
+                "Copies reroll initiative: {}",
+                yes_no(app.encounter.copies.reroll_initiative)
+            ),
+            MetaOption::RemoveDefeated => format!(
+                "Remove defeated creatures outside the party: {}",
+                yes_no(app.encounter.remove_defeated)
+            ),
+            MetaOption::Quit => "Quit".into(),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct App<'a> {
     running: bool,
     mode: Mode,
     selected_creature: Option<usize>,
-    creatures: Vec<Creature>,
+    // Indices picked for the next bulk action. Kept out of the encounter so marking isn't undoable.
+    marked: BTreeSet<usize>,
+    // What undo and redo work on, the rest is how it's being looked at
+    encounter: Encounter,
+    // What changed at the end of the last turn, shown in Mode::TurnSummary
+    turn_summary: Vec<String>,
     text_area: TextArea<'a>,
+    // Dice expression being typed in one of the number input modes
+    input: String,
+    rng: StdRng,
+    save_path: Option<PathBuf>,
+    // Feedback from the last action that had something to say, like saving
+    status: Option<String>,
+    autosave: Option<Autosave>,
+    // Leftovers of a crashed session, waiting for the user to decide on them
+    recovered: Option<SaveFile>,
+    history: History<Encounter>,
+    keymap: Keymap,
+    theme: Theme,
+    bestiary: Bestiary,
+    // Where saving a template writes the bestiary, None keeps it for this session only
+    bestiary_path: Option<PathBuf>,
+    // Size of the last frame, for working out what a click landed on
+    area: Rect,
+    // First creature shown in the table, counted among the ones the filter lets through
+    table_offset: usize,
+    // Only creatures on this side are listed
+    side_filter: Option<Side>,
+    // For a screen the players can see, hides how the GM's creatures are doing in numbers
+    player_view: bool,
+    last_click: Option<(Instant, Position)>,
 }
 
 enum HotKey {
@@ -91,91 +372,188 @@ enum HotKey {
         text: &'static str,
         newline: bool,
     },
-    Embed {
-        pre: &'static str,
-        color: &'static str,
-        post: &'static str,
-    },
     Label {
         label: &'static str,
         keys: &'static str,
     },
+    // Keys looked up from the keymap, so the help follows rebinding
+    Bound {
+        label: &'static str,
+        actions: &'static [Action],
+    },
 }
 
 const HELP_BLURB: &str = "\
 Howdy partner, this is a combat tracker I use for my Pathfinder 2e games.
 It's designed for me and since I'm a bit of a power user, so it's a modal
-system that's exclusively keyboard operated.
+system that's mostly keyboard operated. The creature table takes clicks too.
 
 Normal mode is the most complex. Besides that most modes have like three shoftcuts.
 Most modes have a banner at the bottom with some help.
 
 Best of luck
 ";
+// Picking a degree of success directly, worst first like Degree::ALL
+const OUTCOMES: &[Action] = &[
+    Action::CriticalFailure,
+    Action::Failure,
+    Action::Success,
+    Action::CriticalSuccess,
+];
 const HOTKEYS: &[HotKey] = &[
     HotKey::Divider {
         text: "In normal mode",
         newline: false,
     },
-    HotKey::Label {
+    HotKey::Bound {
         label: "Open this help message",
-        keys: "?",
+        actions: &[Action::Help],
     },
-    HotKey::Label {
-        label: "Quit",
-        keys: "Esc",
+    HotKey::Bound {
+        label: "Menu, for saving and quitting",
+        actions: &[Action::Menu],
     },
-    HotKey::Label {
-        label: "Move",
-        keys: "JjkK",
-    },
-    HotKey::Embed {
-        pre: "",
-        color: "A",
-        post: "dd a creature",
-    },
-    HotKey::Embed {
-        pre: "",
-        color: "R",
-        post: "ename a creature",
-    },
-    HotKey::Embed {
-        pre: "",
-        color: "C",
-        post: "opy (duplicate) a creature",
-    },
-    HotKey::Embed {
-        pre: "",
-        color: "D",
-        post: "elete a creature",
-    },
-    HotKey::Embed {
-        pre: "Set ",
-        color: "i",
-        post: "nitiative of a creature",
-    },
-    HotKey::Embed {
-        pre: "Set ",
-        color: "H",
-        post: "health a creature",
+    HotKey::Bound {
+        label: "Move to the first, previous, next and last creature",
+        actions: &[
+            Action::SelectFirst,
+            Action::SelectPrevious,
+            Action::SelectNext,
+            Action::SelectLast,
+        ],
     },
-    HotKey::Label {
-        label: "Subtract health",
-        keys: "-",
+    HotKey::Bound {
+        label: "Add a creature",
+        actions: &[Action::Add],
     },
-    HotKey::Label {
+    HotKey::Bound {
+        label: "Rename a creature",
+        actions: &[Action::Rename],
+    },
+    HotKey::Bound {
+        label: "Copy (duplicate) a creature, copies are numbered",
+        actions: &[Action::Duplicate],
+    },
+    HotKey::Bound {
+        label: "Add several copies of a creature",
+        actions: &[Action::DuplicateMany],
+    },
+    HotKey::Bound {
+        label: "Delete a creature",
+        actions: &[Action::Delete],
+    },
+    HotKey::Bound {
+        label: "Add a creature from the bestiary",
+        actions: &[Action::AddFromBestiary],
+    },
+    HotKey::Bound {
+        label: "Save a creature to the bestiary",
+        actions: &[Action::SaveTemplate],
+    },
+    HotKey::Bound {
+        label: "Edit notes of a creature",
+        actions: &[Action::EditNotes],
+    },
+    HotKey::Bound {
+        label: "Set initiative of a creature, empty to clear",
+        actions: &[Action::SetInitiative],
+    },
+    HotKey::Bound {
+        label: "Set initiative bonus of a creature",
+        actions: &[Action::SetInitiativeModifier],
+    },
+    HotKey::Bound {
+        label: "Roll initiative for creatures without one",
+        actions: &[Action::RollInitiative],
+    },
+    HotKey::Bound {
+        label: "Switch side between enemy, party, ally and neutral",
+        actions: &[Action::ToggleSide],
+    },
+    HotKey::Bound {
+        label: "Show only one side, cycling through the sides there are",
+        actions: &[Action::FilterSide],
+    },
+    HotKey::Bound {
+        label: "Player view, hiding the health of enemies and neutrals",
+        actions: &[Action::PlayerView],
+    },
+    HotKey::Bound {
+        label: "Mark a creature, or click with ctrl. Most edits then apply to all marked",
+        actions: &[Action::ToggleMark],
+    },
+    HotKey::Bound {
+        label: "Clear the marks",
+        actions: &[Action::ClearMarks],
+    },
+    HotKey::Bound {
+        label: "Move a creature up or down, for breaking ties by hand",
+        actions: &[Action::MoveUp, Action::MoveDown],
+    },
+    HotKey::Bound {
+        label: "Set health of a creature",
+        actions: &[Action::SetHealth],
+    },
+    HotKey::Bound {
+        label: "Set max health of a creature",
+        actions: &[Action::SetMaxHealth],
+    },
+    HotKey::Bound {
+        label: "Set temporary health of a creature",
+        actions: &[Action::SetTempHealth],
+    },
+    HotKey::Bound {
+        label: "Set armor class, saves and perception, like 18 +9 +7 +5 +8",
+        actions: &[Action::SetDefenses],
+    },
+    HotKey::Bound {
+        label: "Set immunities, weaknesses and resistances, like weak fire 5, resist all 2",
+        actions: &[Action::SetAdjustments],
+    },
+    HotKey::Bound {
+        label: "Edit conditions",
+        actions: &[Action::EditConditions],
+    },
+    HotKey::Bound {
+        label: "Deal damage, typed like 2d6+4 slashing, 1d6 fire",
+        actions: &[Action::Damage],
+    },
+    HotKey::Bound {
         label: "Add health",
-        keys: "+",
+        actions: &[Action::Heal],
+    },
+    HotKey::Bound {
+        label: "Damage with a basic save, rolled against a DC or picked by hand",
+        actions: &[Action::BasicSave],
+    },
+    HotKey::Bound {
+        label: "Sort creatures",
+        actions: &[Action::Sort],
+    },
+    HotKey::Bound {
+        label: "Next turn / previous turn",
+        actions: &[Action::NextTurn, Action::PreviousTurn],
+    },
+    HotKey::Bound {
+        label: "Undo / redo",
+        actions: &[Action::Undo, Action::Redo],
     },
-    HotKey::Embed {
-        pre: "",
-        color: "S",
-        post: "ort creatures",
+    HotKey::Label {
+        label: "Select a creature",
+        keys: "Click or scroll",
+    },
+    HotKey::Label {
+        label: "Edit the initiative, name or health of a creature",
+        keys: "Double click",
     },
     HotKey::Divider {
         text: "In most editing modes",
         newline: true,
     },
+    HotKey::Label {
+        label: "Numbers also take dice, like 2d8+4 or 4d6kh3",
+        keys: "0-9 d kh kl + -",
+    },
     HotKey::Label {
         label: "Confirm",
         keys: "Enter",
@@ -185,28 +563,103 @@ const HOTKEYS: &[HotKey] = &[
         keys: "Esc",
     },
     HotKey::Divider {
-        text: "In sort mode (shift inverts direction)",
+        text: "In sort mode",
         newline: true,
     },
-    HotKey::Embed {
-        pre: "Sort by ",
-        color: "I",
-        post: "nitiative",
+    HotKey::Bound {
+        label: "Sort by initiative, or reversed",
+        actions: &[Action::SortByInitiative, Action::SortByInitiativeReversed],
     },
-    HotKey::Embed {
-        pre: "Sort by ",
-        color: "H",
-        post: "ealth",
+    HotKey::Bound {
+        label: "Sort by health, or reversed",
+        actions: &[Action::SortByHealth, Action::SortByHealthReversed],
     },
-    HotKey::Embed {
-        pre: "Sort by ",
-        color: "N",
-        post: "ame",
+    HotKey::Bound {
+        label: "Sort by name, or reversed",
+        actions: &[Action::SortByName, Action::SortByNameReversed],
     },
     HotKey::Label {
         label: "Cancel",
         keys: "Esc",
     },
+    HotKey::Divider {
+        text: "In condition mode",
+        newline: true,
+    },
+    HotKey::Bound {
+        label: "Move",
+        actions: &[
+            Action::SelectFirst,
+            Action::SelectPrevious,
+            Action::SelectNext,
+            Action::SelectLast,
+        ],
+    },
+    HotKey::Bound {
+        label: "Add or increase a condition",
+        actions: &[Action::IncreaseCondition],
+    },
+    HotKey::Bound {
+        label: "Reduce or remove a condition",
+        actions: &[Action::DecreaseCondition],
+    },
+    HotKey::Bound {
+        label: "Cycle end of turn rule (persist, decrement, end)",
+        actions: &[Action::CycleConditionRule],
+    },
+    HotKey::Divider {
+        text: "After basic save damage or damaging marked creatures",
+        newline: true,
+    },
+    HotKey::Bound {
+        label: "Fortitude, reflex or will save, or the next one",
+        actions: &[
+            Action::Fortitude,
+            Action::Reflex,
+            Action::Will,
+            Action::NextSave,
+        ],
+    },
+    HotKey::Label {
+        label: "Roll saves against the typed DC, or pick outcomes by hand without one",
+        keys: "Enter",
+    },
+    HotKey::Bound {
+        label: "Critical failure, failure, success or critical success",
+        actions: OUTCOMES,
+    },
+    HotKey::Bound {
+        label: "Worse or better outcome",
+        actions: &[Action::WorseOutcome, Action::BetterOutcome],
+    },
+    HotKey::Divider {
+        text: "At the start of a dying character's turn",
+        newline: true,
+    },
+    HotKey::Bound {
+        label: "Recovery check critically failed, failed, succeeded or critically succeeded",
+        actions: OUTCOMES,
+    },
+    HotKey::Bound {
+        label: "Worse or better outcome",
+        actions: &[Action::WorseOutcome, Action::BetterOutcome],
+    },
+    HotKey::Divider {
+        text: "At the end of a turn with persistent damage",
+        newline: true,
+    },
+    HotKey::Bound {
+        label: "Flat check failed or passed, for players rolling their own",
+        actions: &[Action::WorseOutcome, Action::BetterOutcome],
+    },
+    HotKey::Label {
+        label: "Take the damage and end what passed its flat check",
+        keys: "Enter",
+    },
+    HotKey::Label {
+        label: "Skip the damage this turn",
+        keys: "Esc",
+    },
     HotKey::Divider {
         text: "In help mode",
         newline: true,
@@ -219,11 +672,13 @@ const HOTKEYS: &[HotKey] = &[
 
 impl App<'_> {
     pub fn new(init_test_creatures: bool) -> App<'static> {
-        App {
+        let mut app = App {
             running: true,
             mode: Mode::Normal,
-            selected_creature: if init_test_creatures { Some(0) } else { None },
-            creatures: if init_test_creatures {
+            selected_creature: None,
+            marked: BTreeSet::new(),
+            turn_summary: vec![],
+            encounter: Encounter::new(if init_test_creatures {
                 vec![
                     Creature {
                         name: "Goblin".into(),
@@ -246,159 +701,464 @@ impl App<'_> {
                 ]
             } else {
                 vec![]
-            },
-            text_area: new_text_area(vec![]),
+            }),
+            text_area: new_text_area(vec![], &Theme::default()),
+            input: String::new(),
+            rng: StdRng::from_os_rng(),
+            save_path: None,
+            status: None,
+            autosave: None,
+            recovered: None,
+            history: History::new(Encounter::default(), history::DEFAULT_DEPTH),
+            keymap: Keymap::default(),
+            theme: Theme::default(),
+            bestiary: Bestiary::default(),
+            bestiary_path: None,
+            area: Rect::default(),
+            table_offset: 0,
+            side_filter: None,
+            player_view: false,
+            last_click: None,
+        };
+        if init_test_creatures {
+            // Selecting loads the notes too
+            app.select_creature(0);
+        }
+        app.history.reset(app.encounter.clone());
+        app
+    }
+
+    pub fn load(path: &Path) -> io::Result<App<'static>> {
+        let save = SaveFile::load(path)?;
+        let mut app = App::new(false);
+        app.apply_save(save);
+        app.save_path = Some(path.to_path_buf());
+        Ok(app)
+    }
+
+    /// Offers to recover a crashed session, also over a loaded encounter unless they match.
+    /// Goes before with_imports so imported creatures end up in whichever encounter is kept.
+    pub fn with_autosave(mut self, autosave: Autosave) -> Self {
+        if let Some(save) = autosave
+            .unclean_session()
+            .filter(|save| self.save_path.is_none() || save.encounter != self.encounter)
+        {
+            self.recovered = Some(save);
+            self.mode = Mode::Recover;
+        }
+        self.autosave = Some(autosave);
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = StdRng::seed_from_u64(seed);
+        self
+    }
+
+    pub fn with_history_depth(mut self, depth: usize) -> Self {
+        self.history.set_depth(depth);
+        self
+    }
+
+    pub fn with_keymap(mut self, keymap: Keymap) -> Self {
+        self.keymap = keymap;
+        self
+    }
+
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.text_area.set_line_number_style(theme.line_numbers);
+        self.theme = theme;
+        self
+    }
+
+    pub fn with_bestiary(mut self, bestiary: Bestiary, path: Option<PathBuf>) -> Self {
+        self.bestiary = bestiary;
+        self.bestiary_path = path;
+        self
+    }
+
+    /// Adds the exported creatures before the session starts, they can't be undone
+    pub fn with_imports(mut self, paths: &[PathBuf]) -> io::Result<Self> {
+        for path in paths {
+            self.import(path)?;
+            // Accepting the recovery prompt keeps them too
+            let imported = self
+                .selected_creature
+                .and_then(|index| self.encounter.creatures.get(index))
+                .cloned();
+            if let (Some(save), Some(creature)) = (self.recovered.as_mut(), imported) {
+                save.selected_creature = Some(save.encounter.add(creature));
+            }
+        }
+        self.history.reset(self.encounter.clone());
+        Ok(self)
+    }
+
+    /// Adds the creature from a Pathbuilder or Foundry export and selects it, returns its name
+    fn import(&mut self, path: &Path) -> io::Result<String> {
+        let creature = import::from_file(path)?;
+        info!("Imported {} from {}", creature.name, path.display());
+        let name = creature.name.clone();
+        let index = self.encounter.add(creature);
+        self.select_creature(index);
+        Ok(name)
+    }
+
+    fn restore(&mut self, encounter: Encounter) {
+        // Undoing a sort or a move puts other creatures at the marked indices
+        self.marked.clear();
+        // Stay on the same creature if it's still there under its name
+        let selected = self.hovered_creature().and_then(|creature| {
+            encounter
+                .creatures
+                .iter()
+                .position(|other| other.name == creature.name)
+        });
+        self.encounter = encounter;
+        match self.selected_creature {
+            _ if self.encounter.creatures.is_empty() => {
+                self.selected_creature = None;
+                self.text_area = new_text_area(vec![], &self.theme);
+            }
+            // Reselect to pick up the restored notes
+            Some(index) => self
+                .select_creature(selected.unwrap_or(index.min(self.encounter.creatures.len() - 1))),
+            None => {}
+        }
+    }
+
+    fn to_save(&self) -> SaveFile {
+        SaveFile {
+            version: SAVE_VERSION,
+            selected_creature: self.selected_creature,
+            encounter: self.encounter.clone(),
+        }
+    }
+
+    fn apply_save(&mut self, save: SaveFile) {
+        self.encounter = save.encounter;
+        self.marked.clear();
+        self.encounter.active_creature = self
+            .encounter
+            .active_creature
+            .filter(|index| *index < self.encounter.creatures.len());
+        self.selected_creature = None;
+        self.text_area = new_text_area(vec![], &self.theme);
+        if let Some(index) = save.selected_creature {
+            if index < self.encounter.creatures.len() {
+                self.select_creature(index);
+            }
+        }
+        self.history.reset(self.encounter.clone());
+    }
+
+    fn save(&mut self, path: PathBuf) {
+        match self.to_save().write(&path) {
+            Ok(()) => {
+                info!("Saved encounter to {}", path.display());
+                self.status = Some(format!("Saved to {}", path.display()));
+                self.save_path = Some(path);
+                self.mode = Mode::Normal;
+            }
+            Err(err) => {
+                info!("Failed to save encounter to {}: {}", path.display(), err);
+                self.status = Some(format!("Failed to save to {}: {}", path.display(), err));
+            }
         }
     }
 
     pub fn run(mut self, mut terminal: DefaultTerminal) -> io::Result<()> {
         while self.running {
-            terminal
+            let frame = terminal
                 .draw(|frame| {
                     frame.render_widget(self.clone(), frame.area());
                 })
                 .expect("failed to draw frame");
-            self.read_events()?;
+            self.area = frame.area;
+            self.handle_event(event::read()?);
+        }
+
+        if let Some(autosave) = self.autosave.as_mut() {
+            autosave.clear()?;
         }
 
         Ok(())
     }
 
+    fn is_editing(&self) -> bool {
+        matches!(
+            self.mode,
+            Mode::Rename(_)
+                | Mode::SetHealth
+                | Mode::SetMaxHealth
+                | Mode::SetTempHealth
+                | Mode::SetInitiative
+                | Mode::SetInitiativeModifier
+                | Mode::HealthShift(_)
+                | Mode::SetDefenses
+                | Mode::SetAdjustments
+                | Mode::EditNotes
+        )
+    }
+
+    fn update_history(&mut self) {
+        // Half finished edits get recorded once they are confirmed
+        if self.is_editing() {
+            return;
+        }
+
+        self.history.record(&self.encounter);
+    }
+
+    fn undo(&mut self) {
+        match self.history.undo() {
+            Some(encounter) => self.restore(encounter),
+            None => self.status = Some("Nothing to undo".into()),
+        }
+    }
+
+    fn redo(&mut self) {
+        match self.history.redo() {
+            Some(encounter) => self.restore(encounter),
+            None => self.status = Some("Nothing to redo".into()),
+        }
+    }
+
+    fn update_autosave(&mut self) {
+        if self.mode == Mode::Recover {
+            // Don't overwrite the old session before the user has decided on it
+            return;
+        }
+
+        let save = self.to_save();
+        if let Some(autosave) = self.autosave.as_mut() {
+            if let Err(err) = autosave.update(&save) {
+                info!("Autosave failed: {}", err);
+                self.status = Some(format!("Autosave failed: {}", err));
+            }
+        }
+    }
+
+    /// Creatures the table lists, all of them unless filtered to one side
+    fn shown(&self) -> Vec<usize> {
+        (0..self.encounter.creatures.len())
+            .filter(|index| {
+                self.side_filter
+                    .is_none_or(|side| self.encounter.creatures[*index].side == side)
+            })
+            .collect()
+    }
+
+    /// Selects the creature at a position in the table, clamped to the last one listed
+    fn select_shown(&mut self, position: usize) {
+        let shown = self.shown();
+        if let Some(index) = shown.get(position).or(shown.last()) {
+            self.select_creature(*index);
+        }
+    }
+
+    /// Position of the selected creature in the table
+    fn selected_position(&self) -> Option<usize> {
+        let selected = self.selected_creature?;
+        self.shown().iter().position(|index| *index == selected)
+    }
+
     fn hovered_creature(&self) -> Option<&Creature> {
         self.selected_creature
-            .and_then(|index| self.creatures.get(index))
+            .and_then(|index| self.encounter.creatures.get(index))
     }
 
     fn hovered_creature_mut(&mut self) -> Option<&mut Creature> {
         self.selected_creature
-            .and_then(|index| self.creatures.get_mut(index))
+            .and_then(|index| self.encounter.creatures.get_mut(index))
     }
 
-    fn read_events(&mut self) -> io::Result<()> {
-        let Event::Key(ev) = event::read()? else {
-            return Ok(());
-        };
+    /// Everything a turn of the main loop does besides drawing
+    fn handle_event(&mut self, event: Event) {
+        match event {
+            Event::Key(ev) => self.handle_key(ev),
+            Event::Mouse(ev) => self.handle_mouse(ev),
+            Event::Resize(width, height) => self.area = Rect::new(0, 0, width, height),
+            _ => {}
+        }
+        self.follow_selection();
+        self.update_history();
+        self.update_autosave();
+    }
+
+    /// Scrolls the creature table just enough to keep the selection in view
+    fn follow_selection(&mut self) {
+        let visible = self.normal_layout(self.area).visible_rows();
+        if visible == 0 {
+            return;
+        }
+
+        // A filter that hides the selection or everything is dropped, edits would go unseen
+        if let Some(side) = self.side_filter {
+            let selected_side = self.hovered_creature().map(|creature| creature.side);
+            if selected_side.is_some_and(|selected| selected != side) || self.shown().is_empty() {
+                self.side_filter = None;
+            }
+        }
+
+        // Don't leave empty rows at the bottom while there are creatures above
+        let shown = self.shown();
+        self.table_offset = self.table_offset.min(shown.len().saturating_sub(visible));
+        let position = self
+            .selected_creature
+            .and_then(|selected| shown.iter().position(|index| *index == selected));
+        if let Some(selected) = position {
+            if selected < self.table_offset {
+                self.table_offset = selected;
+            } else if selected >= self.table_offset + visible {
+                self.table_offset = selected + 1 - visible;
+            }
+        }
+    }
 
+    fn handle_key(&mut self, ev: KeyEvent) {
         info!("Key press - {:?}", ev);
 
+        if ev.kind == KeyEventKind::Press {
+            self.status = None;
+        }
+
         match (&self.mode, ev.kind) {
+            (Mode::Recover, KeyEventKind::Press) => {
+                match self.keymap.action_in(Context::Recover, ev.code) {
+                    Some(Action::RestoreSession) => {
+                        if let Some(save) = self.recovered.take() {
+                            self.apply_save(save);
+                            self.status = Some("Restored the previous session".into());
+                        }
+                        self.mode = Mode::Normal;
+                    }
+                    Some(Action::DiscardSession) => {
+                        self.recovered = None;
+                        self.mode = Mode::Normal;
+                    }
+                    _ => {}
+                }
+            }
             (Mode::Normal, KeyEventKind::Press) => {
+                if let Some(action) = self.keymap.action(ev.code) {
+                    self.perform(action);
+                }
+            }
+            (Mode::Meta(selection), KeyEventKind::Press) => {
+                let selection = *selection;
+                let options = MetaOption::ALL.len();
                 match ev.code {
-                    KeyCode::Esc => self.mode = Mode::Meta(0),
-
-                    KeyCode::Char('?') => self.mode = Mode::Help,
-                    KeyCode::Char('s') => self.mode = Mode::Sort,
-
-                    // Navigation
-                    KeyCode::Char('K') => self.select_creature(0),
-                    KeyCode::Char('k') => self.select_creature({
-                        let curr = self.selected_creature.unwrap_or_default();
-                        if curr == 0 {
-                            self.creatures.len().saturating_sub(1)
-                        } else {
-                            curr - 1
+                    KeyCode::Esc => self.mode = Mode::Normal,
+                    KeyCode::Enter => match MetaOption::ALL[selection] {
+                        MetaOption::Return => self.mode = Mode::Normal,
+                        MetaOption::Save => match self.save_path.clone() {
+                            Some(path) => self.save(path),
+                            None => self.mode = Mode::SaveAs(String::new()),
+                        },
+                        MetaOption::SaveAs => {
+                            self.mode = Mode::SaveAs(
+                                self.save_path
+                                    .as_ref()
+                                    .map(|path| path.display().to_string())
+                                    .unwrap_or_default(),
+                            )
                         }
-                    }),
-                    KeyCode::Char('j') => self.select_creature({
-                        if self.creatures.is_empty() {
-                            0
-                        } else {
-                            (self
-                                .selected_creature
-                                .map(|num| num + 1)
-                                .unwrap_or_default())
-                                % self.creatures.len()
+                        MetaOption::Import => self.mode = Mode::Import(String::new()),
+                        MetaOption::TieBreak => {
+                            self.encounter.tie_break = self.encounter.tie_break.next()
                         }
-                    }),
-                    KeyCode::Char('J') => {
-                        self.select_creature(self.creatures.len().saturating_sub(1))
-                    }
-
-                    // Actions
-                    KeyCode::Char('a') => {
-                        self.creatures.push(Creature {
-                            name: "".into(),
-                            ..Creature::default()
-                        });
-                        self.select_creature(self.creatures.len() - 1);
-                        self.mode = Mode::Rename(String::new());
-                    }
-                    KeyCode::Char('r') => {
-                        if let Some(creat) = self.hovered_creature_mut() {
-                            self.mode = Mode::Rename(creat.name.clone());
+                        MetaOption::Numbering => {
+                            let copies = &mut self.encounter.copies;
+                            copies.numbering = copies.numbering.next()
                         }
-                    }
-                    KeyCode::Char('n') => {
-                        if self.hovered_creature().is_some() {
-                            self.mode = Mode::EditNotes;
+                        MetaOption::RerollHealth => {
+                            let copies = &mut self.encounter.copies;
+                            copies.reroll_health = !copies.reroll_health
                         }
-                    }
-                    KeyCode::Char('c') => {
-                        // TODO: Think about automatically renaming with indices or something
-                        if let Some(hovered) = self.hovered_creature() {
-                            let index = self.selected_creature.unwrap();
-                            let duplicate = hovered.clone();
-                            self.creatures.insert(index + 1, duplicate);
+                        MetaOption::RerollInitiative => {
+                            let copies = &mut self.encounter.copies;
+                            copies.reroll_initiative = !copies.reroll_initiative
                         }
-                    }
-                    KeyCode::Char('d') => {
-                        if self.hovered_creature().is_some() {
-                            let index = self.selected_creature.unwrap();
-                            self.creatures.remove(index);
-                            if self.creatures.is_empty() {
-                                self.selected_creature = None;
-                                self.text_area = new_text_area(vec![]);
-                            } else if self.creatures.len() == index {
-                                // Deleted final element in a non-empty list
-                                self.select_creature(self.creatures.len() - 1);
-                            } else {
-                                // Reselect current index to update notes
-                                self.select_creature(index);
-                            }
+                        MetaOption::RemoveDefeated => {
+                            self.encounter.remove_defeated = !self.encounter.remove_defeated;
+                            self.report_health_changes(vec![]);
                         }
-                    }
-                    KeyCode::Char('h') => {
-                        if let Some(creat) = self.hovered_creature() {
-                            self.mode = Mode::SetHealth(creat.health);
+                        MetaOption::Quit => self.running = false,
+                    },
+                    code => match self.keymap.action(code) {
+                        Some(Action::SelectPrevious) => {
+                            self.mode = Mode::Meta((selection + options - 1) % options)
                         }
-                    }
-                    KeyCode::Char('i') => {
-                        if let Some(creat) = self.hovered_creature() {
-                            self.mode = Mode::SetInitiative(creat.initiative);
+                        Some(Action::SelectNext) => {
+                            self.mode = Mode::Meta((selection + 1) % options)
                         }
+                        _ => {}
+                    },
+                }
+            }
+            (Mode::SaveAs(path), KeyEventKind::Press) => {
+                let mut path = path.clone();
+                match ev.code {
+                    KeyCode::Enter if !path.is_empty() => {
+                        self.save(PathBuf::from(path));
+                        return;
                     }
-                    KeyCode::Char('-') => {
-                        if let Some(creature) = self.hovered_creature_mut() {
-                            creature.health_shift = Some(HealthShift::Decrease(0));
-                            self.mode = Mode::HealthShift;
-                        }
+                    KeyCode::Esc => {
+                        self.mode = Mode::Meta(MetaOption::SaveAs.index());
+                        return;
+                    }
+                    KeyCode::Backspace => {
+                        path.pop();
                     }
-                    KeyCode::Char('+') => {
-                        if let Some(creature) = self.hovered_creature_mut() {
-                            creature.health_shift = Some(HealthShift::Increase(0));
-                            self.mode = Mode::HealthShift;
+                    KeyCode::Char(ch) => {
+                        path.push(ch);
+                    }
+                    _ => {}
+                }
+                self.mode = Mode::SaveAs(path);
+            }
+            (Mode::Import(path), KeyEventKind::Press) => {
+                let mut path = path.clone();
+                match ev.code {
+                    KeyCode::Enter if !path.is_empty() => {
+                        match self.import(Path::new(&path)) {
+                            Ok(name) => {
+                                self.status = Some(format!("Imported {}", name));
+                                self.mode = Mode::Normal;
+                            }
+                            Err(err) => self.status = Some(format!("Can't import: {}", err)),
                         }
+                        return;
+                    }
+                    KeyCode::Esc => {
+                        self.mode = Mode::Meta(MetaOption::Import.index());
+                        return;
+                    }
+                    KeyCode::Backspace => {
+                        path.pop();
+                    }
+                    KeyCode::Char(ch) => {
+                        path.push(ch);
                     }
                     _ => {}
                 }
+                self.mode = Mode::Import(path);
             }
-            (Mode::Meta(selection), KeyEventKind::Press) => match ev.code {
-                KeyCode::Esc => self.mode = Mode::Normal,
-                KeyCode::Enter if *selection == 0 => self.mode = Mode::Normal,
-                KeyCode::Enter if *selection == 1 => self.running = false,
-                // Since there are only two options, we can just do this
-                KeyCode::Char('k') | KeyCode::Char('j') => self.mode = Mode::Meta(1 - selection),
-                _ => {}
-            },
             (Mode::Rename(old_name), KeyEventKind::Press) => {
-                let mut name = self.hovered_creature().unwrap().name.clone();
+                let old_name = old_name.clone();
+                // Nothing to rename once the list is empty
+                let Some(mut name) = self.hovered_creature().map(|creat| creat.name.clone()) else {
+                    self.mode = Mode::Normal;
+                    return;
+                };
                 match ev.code {
                     KeyCode::Enter => {
                         self.mode = Mode::Normal;
                     }
                     KeyCode::Esc => {
                         // Revert name
-                        name = old_name.clone();
+                        name = old_name;
                         self.mode = Mode::Normal;
                     }
                     KeyCode::Backspace => {
@@ -410,16 +1170,19 @@ impl App<'_> {
 
                     _ => {}
                 }
-                self.hovered_creature_mut().unwrap().name = name;
+                if let Some(creature) = self.hovered_creature_mut() {
+                    creature.name = name;
+                }
             }
             // This accepts all key events
             (Mode::EditNotes, _) => match (ev.code, ev.kind) {
                 (KeyCode::Esc, KeyEventKind::Press) => {
                     let notes = self.text_area.lines().join("\n");
                     let cursor_pos = self.text_area.cursor();
-                    let creature = self.hovered_creature_mut().unwrap();
-                    creature.notes = notes;
-                    creature.notes_cursor_pos = cursor_pos;
+                    if let Some(creature) = self.hovered_creature_mut() {
+                        creature.notes = notes;
+                        creature.notes_cursor_pos = cursor_pos;
+                    }
                     self.mode = Mode::Normal;
                 }
 
@@ -427,93 +1190,971 @@ impl App<'_> {
                     self.text_area.input(ev);
                 }
             },
-            (Mode::SetHealth(old_amount), KeyEventKind::Press) => {
-                let old = *old_amount;
-                self.numeric_edit(
-                    |creature| creature.health,
-                    |creature, value| creature.health = value,
-                    |creature| creature.health = old,
-                    |_| {},
-                    ev,
-                );
+            (Mode::SetHealth, KeyEventKind::Press) => {
+                self.health_edit(|creature, value| creature.set_health(value), ev);
             }
-            (Mode::SetInitiative(old_amount), KeyEventKind::Press) => {
-                let old = *old_amount;
-                self.numeric_edit(
-                    |creature| creature.initiative,
-                    |creature, value| creature.initiative = value,
-                    |creature| creature.initiative = old,
-                    |_| {},
+            (Mode::SetMaxHealth, KeyEventKind::Press) => {
+                // Remembered so copies can roll their own
+                let dice = Expression::parse(&self.input)
+                    .ok()
+                    .filter(|expression| !expression.is_constant())
+                    .map(|_| self.input.trim().to_string());
+                self.expression_edit(
+                    move |creature, value| {
+                        // Fresh creatures start at full health, downed ones stay down
+                        if creature.max_health == 0 && creature.health == 0 {
+                            creature.health = value;
+                        }
+                        creature.max_health = value;
+                        creature.health_dice = dice.clone();
+                    },
                     ev,
                 );
             }
-            (Mode::HealthShift, KeyEventKind::Press) => {
-                self.numeric_edit(
-                    |creature| match creature.health_shift.unwrap() {
-                        HealthShift::Increase(mag) | HealthShift::Decrease(mag) => mag as i32,
-                    },
-                    |creature, value| match creature.health_shift.as_mut().unwrap() {
-                        HealthShift::Increase(ref mut mag) | HealthShift::Decrease(ref mut mag) => {
-                            *mag = value as u32
-                        }
-                    },
-                    |creature| creature.health_shift = None,
-                    |creature| {
-                        match creature.health_shift.unwrap() {
-                            HealthShift::Increase(mag) => creature.health += mag as i32,
-                            HealthShift::Decrease(mag) => creature.health -= mag as i32,
-                        }
-                        creature.health_shift = None;
-                    },
+            (Mode::SetTempHealth, KeyEventKind::Press) => {
+                self.expression_edit(
+                    |creature, value| creature.temp_health = value.max(0) as u32,
                     ev,
                 );
             }
-            (Mode::Help, KeyEventKind::Press) => {
-                if ev.code == KeyCode::Esc {
+            (Mode::SetInitiative, KeyEventKind::Press) => {
+                if ev.code == KeyCode::Enter && self.input.trim().is_empty() {
+                    // Clearing the initiative lets it be rolled again
+                    self.for_each_target(|creature| creature.initiative = None);
                     self.mode = Mode::Normal;
+                } else {
+                    self.expression_edit(|creature, value| creature.initiative = Some(value), ev);
                 }
             }
-            (Mode::Sort, KeyEventKind::Press) => match ev.code {
-                KeyCode::Esc => {
-                    self.mode = Mode::Normal;
-                }
-
-                // Initiative
-                KeyCode::Char('i') => {
-                    self.creatures
-                        .sort_by(|a, b| a.initiative.cmp(&b.initiative));
-                    self.mode = Mode::Normal;
-                }
-                KeyCode::Char('I') => {
-                    self.creatures
-                        .sort_by(|b, a| a.initiative.cmp(&b.initiative));
-                    self.mode = Mode::Normal;
+            (Mode::SetInitiativeModifier, KeyEventKind::Press) => {
+                self.expression_edit(|creature, value| creature.initiative_modifier = value, ev);
+            }
+            (Mode::HealthShift(HealthShift::Decrease(_)), KeyEventKind::Press)
+            | (Mode::BasicSave, KeyEventKind::Press)
+                if ev.code == KeyCode::Enter
+                    && (self.mode == Mode::BasicSave || self.marked_count() > 0) =>
+            {
+                // Basic save damage and area damage ask how everyone saved before applying it
+                let Some(damage) = self.roll_damage() else {
+                    return;
+                };
+                self.input.clear();
+                self.mode = Mode::SaveDc {
+                    damage,
+                    targets: self.targets(),
+                    save: SaveKind::default(),
+                };
+            }
+            (Mode::HealthShift(HealthShift::Decrease(_)), KeyEventKind::Press)
+                if ev.code == KeyCode::Enter =>
+            {
+                let Some(damage) = self.roll_damage() else {
+                    return;
+                };
+                self.input.clear();
+                self.mode = Mode::Normal;
+                let hits = self
+                    .targets()
+                    .into_iter()
+                    .map(|index| (index, damage.clone()))
+                    .collect();
+                self.deal_damage(hits);
+            }
+            (Mode::HealthShift(HealthShift::Decrease(_)), KeyEventKind::Press)
+            | (Mode::BasicSave, KeyEventKind::Press) => self.type_damage(ev),
+            (
+                Mode::SaveDc {
+                    damage,
+                    targets,
+                    save,
+                },
+                KeyEventKind::Press,
+            ) => {
+                let (damage, targets, mut save) = (damage.clone(), targets.clone(), *save);
+                match ev.code {
+                    KeyCode::Enter if self.input.trim().is_empty() => {
+                        // Everyone fails until told otherwise
+                        self.mode = Mode::SaveOutcomes {
+                            damage,
+                            save: None,
+                            outcomes: targets
+                                .into_iter()
+                                .map(|index| SaveOutcome {
+                                    index,
+                                    degree: Degree::Failure,
+                                    check: None,
+                                })
+                                .collect(),
+                            selection: 0,
+                        };
+                        return;
+                    }
+                    KeyCode::Enter => {
+                        let Ok(dc) = self.input.trim().parse() else {
+                            self.status = Some(format!("'{}' is not a DC", self.input.trim()));
+                            return;
+                        };
+                        self.input.clear();
+                        self.roll_saves(damage, targets, save, dc);
+                        return;
+                    }
+                    KeyCode::Esc => {
+                        self.input.clear();
+                        self.mode = Mode::Normal;
+                        return;
+                    }
+                    code if self.keymap.action_in(Context::SaveDc, code).is_some() => {
+                        match self.keymap.action_in(Context::SaveDc, code) {
+                            Some(Action::NextSave) => save = save.next(),
+                            Some(Action::Fortitude) => save = SaveKind::Fortitude,
+                            Some(Action::Reflex) => save = SaveKind::Reflex,
+                            Some(Action::Will) => save = SaveKind::Will,
+                            _ => {}
+                        }
+                    }
+                    KeyCode::Backspace => {
+                        self.input.pop();
+                    }
+                    KeyCode::Char(ch) if ch.is_ascii_digit() => self.input.push(ch),
+                    _ => {}
+                }
+                self.mode = Mode::SaveDc {
+                    damage,
+                    targets,
+                    save,
+                };
+            }
+            (
+                Mode::SaveOutcomes {
+                    damage,
+                    save,
+                    outcomes,
+                    selection,
+                },
+                KeyEventKind::Press,
+            ) => {
+                let (damage, save, mut outcomes, mut selection) =
+                    (damage.clone(), *save, outcomes.clone(), *selection);
+                let last = outcomes.len().saturating_sub(1);
+                match ev.code {
+                    KeyCode::Esc => {
+                        self.mode = Mode::Normal;
+                        return;
+                    }
+                    KeyCode::Enter => {
+                        self.apply_save_outcomes(&damage, &outcomes);
+                        self.mode = Mode::Normal;
+                        return;
+                    }
+                    // Overriding a rolled outcome, for rerolls and abilities the tracker doesn't know
+                    code if self.keymap.action_in(Context::Outcome, code).is_some() => {
+                        let degree = &mut outcomes[selection].degree;
+                        match self.keymap.action_in(Context::Outcome, code) {
+                            Some(Action::WorseOutcome) => *degree = degree.worse(),
+                            Some(Action::BetterOutcome) => *degree = degree.better(),
+                            action => {
+                                if let Some(picked) = action.and_then(picked_degree) {
+                                    *degree = picked;
+                                    selection = (selection + 1).min(last);
+                                }
+                            }
+                        }
+                    }
+                    code => match self.keymap.action(code) {
+                        Some(Action::SelectFirst) => selection = 0,
+                        Some(Action::SelectPrevious) => selection = selection.saturating_sub(1),
+                        Some(Action::SelectNext) => selection = (selection + 1).min(last),
+                        Some(Action::SelectLast) => selection = last,
+                        _ => {}
+                    },
                 }
+                self.mode = Mode::SaveOutcomes {
+                    damage,
+                    save,
+                    outcomes,
+                    selection,
+                };
+            }
+            (
+                Mode::PersistentDamage {
+                    creature,
+                    ticks,
+                    selection,
+                },
+                KeyEventKind::Press,
+            ) => {
+                let (creature, mut ticks, mut selection) = (*creature, ticks.clone(), *selection);
+                let last = ticks.len().saturating_sub(1);
+                match ev.code {
+                    KeyCode::Enter => {
+                        if let Some(creature) = self.encounter.creatures.get_mut(creature) {
+                            let name = creature.name.clone();
+                            self.turn_summary.extend(
+                                creature
+                                    .resolve_persistent(&ticks)
+                                    .into_iter()
+                                    .map(|change| format!("{}: {}", name, change)),
+                            );
+                        }
+                        self.report_health_changes(vec![]);
+                        self.show_turn_summary();
+                        return;
+                    }
+                    KeyCode::Esc => {
+                        self.show_turn_summary();
+                        return;
+                    }
+                    code => match self
+                        .keymap
+                        .action_in(Context::Outcome, code)
+                        .or(self.keymap.action(code))
+                    {
+                        Some(Action::WorseOutcome) => ticks[selection].ends = false,
+                        Some(Action::BetterOutcome) => ticks[selection].ends = true,
+                        Some(Action::SelectFirst) => selection = 0,
+                        Some(Action::SelectPrevious) => selection = selection.saturating_sub(1),
+                        Some(Action::SelectNext) => selection = (selection + 1).min(last),
+                        Some(Action::SelectLast) => selection = last,
+                        _ => {}
+                    },
+                }
+                self.mode = Mode::PersistentDamage {
+                    creature,
+                    ticks,
+                    selection,
+                };
+            }
+            (Mode::HealthShift(shift), KeyEventKind::Press) => {
+                let shift = *shift;
+                self.health_edit(
+                    |creature, value| {
+                        creature.apply_health_shift(shift.with_magnitude(value.max(0) as u32))
+                    },
+                    ev,
+                );
+            }
+            (Mode::SetDefenses, KeyEventKind::Press) => self.defenses_edit(ev),
+            (Mode::SetAdjustments, KeyEventKind::Press) => self.adjustments_edit(ev),
+            (Mode::Copies, KeyEventKind::Press) if ev.code == KeyCode::Enter => {
+                let Some(count) = self.roll_input() else {
+                    return;
+                };
+                if !(1..=MAX_COPIES).contains(&count) {
+                    self.status = Some(format!("Can make 1 to {} copies at a time", MAX_COPIES));
+                    return;
+                }
+                self.input.clear();
+                self.mode = Mode::Normal;
+                self.duplicate(count as usize);
+            }
+            (Mode::Copies, KeyEventKind::Press) => self.type_expression(ev),
+            (Mode::Bestiary { query, selection }, KeyEventKind::Press) => {
+                let (mut query, mut selection) = (query.clone(), *selection);
+                let matches = self.bestiary.search(&query);
+                match ev.code {
+                    KeyCode::Esc => {
+                        self.mode = Mode::Normal;
+                        return;
+                    }
+                    KeyCode::Enter => {
+                        let Some(template) = matches
+                            .get(selection)
+                            .map(|index| &self.bestiary.templates[*index])
+                        else {
+                            self.status =
+                                Some(format!("Nothing in the bestiary matches '{}'", query));
+                            return;
+                        };
+                        let name = template.name.clone();
+                        let index = self.encounter.add(template.instantiate());
+                        self.select_creature(index);
+                        self.status = Some(format!("Added {} from the bestiary", name));
+                        self.mode = Mode::Normal;
+                        return;
+                    }
+                    KeyCode::Up => selection = selection.saturating_sub(1),
+                    KeyCode::Down => {
+                        selection = (selection + 1).min(matches.len().saturating_sub(1))
+                    }
+                    KeyCode::Backspace => {
+                        query.pop();
+                        selection = 0;
+                    }
+                    KeyCode::Char(ch) => {
+                        query.push(ch);
+                        selection = 0;
+                    }
+                    _ => {}
+                }
+                self.mode = Mode::Bestiary { query, selection };
+            }
+            (Mode::Conditions(index), KeyEventKind::Press) => {
+                let index = *index;
+                let kind = ConditionKind::ALL[index];
+                let options = ConditionKind::ALL.len();
+                match ev.code {
+                    KeyCode::Esc => self.mode = Mode::Normal,
+                    code => match self
+                        .keymap
+                        .action_in(Context::Conditions, code)
+                        .or(self.keymap.action(code))
+                    {
+                        Some(Action::IncreaseCondition) => self.for_each_target(|creature| {
+                            conditions::increase(&mut creature.conditions, kind)
+                        }),
+                        Some(Action::DecreaseCondition) => self.for_each_target(|creature| {
+                            conditions::decrease(&mut creature.conditions, kind)
+                        }),
+                        Some(Action::CycleConditionRule) => self.for_each_target(|creature| {
+                            conditions::cycle_rule(&mut creature.conditions, kind)
+                        }),
+                        Some(Action::SelectFirst) => self.mode = Mode::Conditions(0),
+                        Some(Action::SelectPrevious) => {
+                            self.mode = Mode::Conditions((index + options - 1) % options)
+                        }
+                        Some(Action::SelectNext) => {
+                            self.mode = Mode::Conditions((index + 1) % options)
+                        }
+                        Some(Action::SelectLast) => self.mode = Mode::Conditions(options - 1),
+                        _ => {}
+                    },
+                }
+            }
+            (Mode::TurnSummary, KeyEventKind::Press)
+                if matches!(ev.code, KeyCode::Enter | KeyCode::Esc) =>
+            {
+                self.turn_summary.clear();
+                self.start_turn();
+            }
+            (
+                Mode::RecoveryCheck {
+                    creature,
+                    check,
+                    degree,
+                },
+                KeyEventKind::Press,
+            ) => {
+                let (creature, check, mut degree) = (*creature, *check, *degree);
+                match ev.code {
+                    KeyCode::Enter => {
+                        let change = self.encounter.creatures.get_mut(creature).map(|creature| {
+                            let change = creature.recover(degree);
+                            format!("{} {}", creature.name, change)
+                        });
+                        self.mode = Mode::Normal;
+                        self.status = None;
+                        self.report_health_changes(change.into_iter().collect());
+                        return;
+                    }
+                    KeyCode::Esc => {
+                        self.mode = Mode::Normal;
+                        return;
+                    }
+                    code => match self.keymap.action_in(Context::Outcome, code) {
+                        Some(Action::WorseOutcome) => degree = degree.worse(),
+                        Some(Action::BetterOutcome) => degree = degree.better(),
+                        action => degree = action.and_then(picked_degree).unwrap_or(degree),
+                    },
+                }
+                self.mode = Mode::RecoveryCheck {
+                    creature,
+                    check,
+                    degree,
+                };
+            }
+            (Mode::Help, KeyEventKind::Press) if ev.code == KeyCode::Esc => {
+                self.mode = Mode::Normal;
+            }
+            (Mode::Sort, KeyEventKind::Press) if ev.code == KeyCode::Esc => {
+                self.mode = Mode::Normal;
+            }
+            (Mode::Sort, KeyEventKind::Press) => {
+                match self.keymap.action_in(Context::Sort, ev.code) {
+                    Some(Action::SortByInitiative) => self.sort_by_initiative(false),
+                    Some(Action::SortByInitiativeReversed) => self.sort_by_initiative(true),
+                    Some(Action::SortByHealth) => {
+                        self.sort_creatures(|a, b| a.health.cmp(&b.health))
+                    }
+                    Some(Action::SortByHealthReversed) => {
+                        self.sort_creatures(|a, b| b.health.cmp(&a.health))
+                    }
+                    Some(Action::SortByName) => self.sort_creatures(|a, b| a.name.cmp(&b.name)),
+                    Some(Action::SortByNameReversed) => {
+                        self.sort_creatures(|a, b| b.name.cmp(&a.name))
+                    }
+                    _ => return,
+                }
+                self.mode = Mode::Normal;
+            }
+            _ => {}
+        }
+    }
 
-                KeyCode::Char('h') => {
-                    self.creatures.sort_by(|a, b| a.health.cmp(&b.health));
-                    self.mode = Mode::Normal;
+    fn handle_mouse(&mut self, ev: MouseEvent) {
+        // Only normal mode, clicking away from a half typed edit is too easy
+        if self.mode != Mode::Normal {
+            return;
+        }
+
+        let layout = self.normal_layout(self.area);
+        let position = Position::new(ev.column, ev.row);
+        match ev.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                let Some(index) = self.creature_at(&layout, position) else {
+                    return;
+                };
+                self.status = None;
+
+                // The terminal only reports presses, double clicks are up to us
+                let double_click = self.last_click.is_some_and(|(time, last)| {
+                    last == position && time.elapsed() < DOUBLE_CLICK_TIME
+                });
+                self.last_click = Some((Instant::now(), position));
+
+                if self.selected_creature != Some(index) {
+                    self.select_creature(index);
                 }
-                KeyCode::Char('H') => {
-                    self.creatures.sort_by(|b, a| a.health.cmp(&b.health));
-                    self.mode = Mode::Normal;
+                if ev.modifiers.contains(KeyModifiers::CONTROL) {
+                    self.perform(Action::ToggleMark);
+                } else if double_click {
+                    self.last_click = None;
+                    match layout
+                        .columns
+                        .iter()
+                        .position(|column| column.contains(position))
+                    {
+                        Some(0) => self.perform(Action::SetInitiative),
+                        Some(1) => self.perform(Action::Rename),
+                        Some(2) | Some(3) => self.perform(Action::SetHealth),
+                        _ => {}
+                    }
                 }
+            }
+            // Scrolling stops at the ends instead of wrapping around like j and k
+            MouseEventKind::ScrollDown if layout.table.contains(position) => {
+                let next = self.selected_position().map_or(0, |position| position + 1);
+                self.select_shown(next);
+            }
+            MouseEventKind::ScrollUp if layout.table.contains(position) => {
+                let previous = self.selected_position().unwrap_or_default();
+                self.select_shown(previous.saturating_sub(1));
+            }
+            _ => {}
+        }
+    }
 
-                KeyCode::Char('n') => {
-                    self.creatures.sort_by(|a, b| a.name.cmp(&b.name));
-                    self.mode = Mode::Normal;
+    fn creature_at(&self, layout: &NormalLayout, position: Position) -> Option<usize> {
+        let rows = Block::bordered().inner(layout.table);
+        if !rows.contains(position) {
+            return None;
+        }
+        self.shown()
+            .get(self.table_offset + (position.y - rows.y) as usize)
+            .copied()
+    }
+
+    /// Does what the action says in normal mode, for keys and clicks alike
+    fn perform(&mut self, action: Action) {
+        match action {
+            Action::Menu => self.mode = Mode::Meta(0),
+
+            Action::Help => self.mode = Mode::Help,
+            Action::Sort => self.mode = Mode::Sort,
+
+            // Navigation
+            Action::SelectFirst => self.select_shown(0),
+            Action::SelectPrevious => self.select_shown({
+                let curr = self.selected_position().unwrap_or_default();
+                if curr == 0 {
+                    self.shown().len().saturating_sub(1)
+                } else {
+                    curr - 1
                 }
-                KeyCode::Char('N') => {
-                    self.creatures.sort_by(|b, a| a.name.cmp(&b.name));
-                    self.mode = Mode::Normal;
+            }),
+            Action::SelectNext => self.select_shown({
+                let shown = self.shown().len();
+                if shown == 0 {
+                    0
+                } else {
+                    (self
+                        .selected_position()
+                        .map(|num| num + 1)
+                        .unwrap_or_default())
+                        % shown
                 }
+            }),
+            Action::SelectLast => self.select_shown(usize::MAX),
+            Action::MoveUp => self.move_creature(false),
+            Action::MoveDown => self.move_creature(true),
 
-                _ => {}
-            },
+            // Turn order
+            Action::NextTurn => self.next_turn(),
+            Action::PreviousTurn => self.encounter.previous_turn(),
+
+            // History
+            Action::Undo => self.undo(),
+            Action::Redo => self.redo(),
+
+            // Actions
+            Action::Add => {
+                // Stays in view when only one side is shown
+                let index = self.encounter.add(Creature {
+                    side: self.side_filter.unwrap_or_default(),
+                    ..Default::default()
+                });
+                self.select_creature(index);
+                self.mode = Mode::Rename(String::new());
+            }
+            Action::Rename => {
+                if let Some(creat) = self.hovered_creature_mut() {
+                    self.mode = Mode::Rename(creat.name.clone());
+                }
+            }
+            Action::EditNotes if self.hovered_creature().is_some() => {
+                self.mode = Mode::EditNotes;
+            }
+            Action::EditConditions if self.hovered_creature().is_some() => {
+                self.mode = Mode::Conditions(0);
+            }
+            Action::Duplicate => self.duplicate(1),
+            Action::DuplicateMany if self.hovered_creature().is_some() => {
+                self.input.clear();
+                self.mode = Mode::Copies;
+            }
+            Action::Delete if self.hovered_creature().is_some() => {
+                self.delete_creatures(self.targets());
+            }
+            Action::ToggleMark => {
+                if let Some(index) = self.selected_creature {
+                    if !self.marked.remove(&index) {
+                        self.marked.insert(index);
+                    }
+                }
+            }
+            Action::ClearMarks => self.marked.clear(),
+            Action::SetHealth => {
+                if let Some(creat) = self.hovered_creature() {
+                    self.input = creat.health.to_string();
+                    self.mode = Mode::SetHealth;
+                }
+            }
+            Action::SetMaxHealth => {
+                if let Some(creat) = self.hovered_creature() {
+                    self.input = creat.max_health.to_string();
+                    self.mode = Mode::SetMaxHealth;
+                }
+            }
+            Action::SetTempHealth => {
+                if let Some(creat) = self.hovered_creature() {
+                    self.input = creat.temp_health.to_string();
+                    self.mode = Mode::SetTempHealth;
+                }
+            }
+            Action::SetInitiative => {
+                if let Some(creat) = self.hovered_creature() {
+                    self.input = creat
+                        .initiative
+                        .map(|initiative| initiative.to_string())
+                        .unwrap_or_default();
+                    self.mode = Mode::SetInitiative;
+                }
+            }
+            Action::SetInitiativeModifier => {
+                if let Some(creat) = self.hovered_creature() {
+                    self.input = creat.initiative_modifier.to_string();
+                    self.mode = Mode::SetInitiativeModifier;
+                }
+            }
+            Action::RollInitiative => self.roll_initiative(),
+            Action::ToggleSide => {
+                self.for_each_target(|creature| creature.side = creature.side.next())
+            }
+            Action::FilterSide => {
+                // Only sides somebody is on, then back to everyone
+                let start = self.side_filter.map_or(0, |side| {
+                    Side::ALL.iter().position(|other| *other == side).unwrap() + 1
+                });
+                self.side_filter = Side::ALL[start..].iter().copied().find(|side| {
+                    self.encounter
+                        .creatures
+                        .iter()
+                        .any(|creature| creature.side == *side)
+                });
+                if let Some(side) = self.side_filter {
+                    if self
+                        .hovered_creature()
+                        .is_none_or(|creature| creature.side != side)
+                    {
+                        self.select_shown(0);
+                    }
+                }
+            }
+            Action::PlayerView => self.player_view = !self.player_view,
+            Action::SetDefenses => {
+                if let Some(creat) = self.hovered_creature() {
+                    self.input = format!(
+                        "{} {:+} {:+} {:+} {:+}",
+                        creat.armor_class,
+                        creat.saves.fortitude,
+                        creat.saves.reflex,
+                        creat.saves.will,
+                        creat.perception
+                    );
+                    self.mode = Mode::SetDefenses;
+                }
+            }
+            Action::SetAdjustments => {
+                if let Some(creat) = self.hovered_creature() {
+                    self.input = creat.adjustments.to_string();
+                    self.mode = Mode::SetAdjustments;
+                }
+            }
+            Action::AddFromBestiary if self.bestiary.templates.is_empty() => {
+                self.status = Some(format!(
+                    "The bestiary is empty, save a creature to it with {}",
+                    self.keymap.describe(&[Action::SaveTemplate])
+                ));
+            }
+            Action::AddFromBestiary => {
+                self.mode = Mode::Bestiary {
+                    query: String::new(),
+                    selection: 0,
+                }
+            }
+            Action::SaveTemplate => self.save_template(),
+            Action::Damage if self.hovered_creature().is_some() => {
+                self.input.clear();
+                self.mode = Mode::HealthShift(HealthShift::Decrease(0));
+            }
+            Action::BasicSave if self.hovered_creature().is_some() => {
+                self.input.clear();
+                self.mode = Mode::BasicSave;
+            }
+            Action::Heal if self.hovered_creature().is_some() => {
+                self.input.clear();
+                self.mode = Mode::HealthShift(HealthShift::Increase(0));
+            }
             _ => {}
         }
+    }
 
-        Ok(())
+    fn sort_creatures(&mut self, compare: impl Fn(&Creature, &Creature) -> Ordering) {
+        let new_indices = self.encounter.sort_by(compare);
+        self.follow_sort(&new_indices);
+    }
+
+    fn sort_by_initiative(&mut self, descending: bool) {
+        let new_indices = self.encounter.sort_by_initiative(descending);
+        self.follow_sort(&new_indices);
+    }
+
+    /// Keeps the selection and the marks on the same creatures after sorting
+    fn follow_sort(&mut self, new_indices: &[usize]) {
+        self.selected_creature = self.selected_creature.map(|index| new_indices[index]);
+        self.marked = self
+            .marked
+            .iter()
+            .map(|index| new_indices[*index])
+            .collect();
+    }
+
+    /// Swaps the selected creature with a neighbour, for settling ties by hand
+    fn move_creature(&mut self, down: bool) {
+        let Some(index) = self.selected_creature else {
+            return;
+        };
+        let Some(other) = (if down {
+            Some(index + 1).filter(|other| *other < self.encounter.creatures.len())
+        } else {
+            index.checked_sub(1)
+        }) else {
+            return;
+        };
+
+        self.encounter.swap(index, other);
+        self.selected_creature = Some(other);
+        let (index_marked, other_marked) = (self.marked.remove(&index), self.marked.remove(&other));
+        if index_marked {
+            self.marked.insert(other);
+        }
+        if other_marked {
+            self.marked.insert(index);
+        }
+    }
+
+    /// Rolls initiative for everyone without one and sorts by the result
+    fn roll_initiative(&mut self) {
+        let results: Vec<String> = self
+            .encounter
+            .roll_initiative(&mut self.rng)
+            .into_iter()
+            .map(|(index, roll)| {
+                let name = &self.encounter.creatures[index].name;
+                info!("Initiative for {}: {}", name, roll);
+                format!("{} {}", name, roll.total)
+            })
+            .collect();
+
+        if results.is_empty() {
+            self.status = Some("Everyone already has initiative".into());
+            return;
+        }
+
+        self.status = Some(format!("Rolled initiative: {}", results.join(", ")));
+        self.sort_by_initiative(true);
+    }
+
+    fn next_turn(&mut self) {
+        let ending = self.encounter.active_creature;
+        self.turn_summary = self.encounter.next_turn();
+
+        // Persistent damage comes first, the summary waits until it's applied
+        if let Some(index) = ending {
+            let creature = &self.encounter.creatures[index];
+            if !creature.persistent.is_empty() {
+                self.mode = Mode::PersistentDamage {
+                    creature: index,
+                    ticks: creature.roll_persistent(&mut self.rng),
+                    selection: 0,
+                };
+                return;
+            }
+        }
+        self.show_turn_summary();
+    }
+
+    fn show_turn_summary(&mut self) {
+        if self.turn_summary.is_empty() {
+            self.start_turn();
+        } else {
+            self.mode = Mode::TurnSummary;
+        }
+    }
+
+    /// Rolls the recovery check if the creature whose turn it is is dying
+    fn start_turn(&mut self) {
+        self.mode = Mode::Normal;
+        let Some(index) = self.encounter.active_creature else {
+            return;
+        };
+        let creature = &self.encounter.creatures[index];
+        if creature.is_dying() {
+            let check = Check::roll(0, creature.recovery_dc(), &mut self.rng);
+            self.mode = Mode::RecoveryCheck {
+                creature: index,
+                check,
+                degree: check.degree,
+            };
+        }
+    }
+
+    /// Marked creatures, or the hovered one when nothing is marked
+    fn targets(&self) -> Vec<usize> {
+        let marked: Vec<usize> = self
+            .marked
+            .iter()
+            .copied()
+            .filter(|index| *index < self.encounter.creatures.len())
+            .collect();
+        if marked.is_empty() {
+            self.selected_creature
+                .filter(|index| *index < self.encounter.creatures.len())
+                .into_iter()
+                .collect()
+        } else {
+            marked
+        }
+    }
+
+    fn for_each_target(&mut self, change: impl Fn(&mut Creature)) {
+        for index in self.targets() {
+            change(&mut self.encounter.creatures[index]);
+        }
+    }
+
+    fn marked_count(&self) -> usize {
+        self.marked.len()
+    }
+
+    /// Rolls the save for every target and shows the outcomes for confirming
+    fn roll_saves(&mut self, damage: Damage, targets: Vec<usize>, save: SaveKind, dc: i32) {
+        let outcomes = targets
+            .into_iter()
+            .map(|index| {
+                let creature = &self.encounter.creatures[index];
+                let check = Check::roll(creature.saves.get(save), dc, &mut self.rng);
+                info!(
+                    "{} rolled {} on a DC {} {} save: {}",
+                    creature.name, check, dc, save, check.degree
+                );
+                SaveOutcome {
+                    index,
+                    degree: check.degree,
+                    check: Some(check),
+                }
+            })
+            .collect();
+        self.mode = Mode::SaveOutcomes {
+            damage,
+            save: Some((save, dc)),
+            outcomes,
+            selection: 0,
+        };
+    }
+
+    /// Deals basic save damage to each creature according to how it saved
+    fn apply_save_outcomes(&mut self, damage: &Damage, outcomes: &[SaveOutcome]) {
+        let hits = outcomes
+            .iter()
+            .map(|outcome| {
+                let degree = outcome.degree;
+                let mut damage = damage.scaled(|amount| degree.basic_damage(amount));
+                // Persistent damage sticks to those who failed
+                if degree >= Degree::Success {
+                    damage.persistent.clear();
+                }
+                (outcome.index, damage)
+            })
+            .collect();
+        self.deal_damage(hits);
+    }
+
+    /// Deals damage after each creature's immunities, weaknesses and resistances.
+    /// Says who took what unless it's a single creature taking it all as rolled.
+    fn deal_damage(&mut self, hits: Vec<(usize, Damage)>) {
+        let mut taken = vec![];
+        let mut adjusted = false;
+        let mut changes = vec![];
+        for (index, damage) in &hits {
+            let Some(creature) = self.encounter.creatures.get_mut(*index) else {
+                continue;
+            };
+            let result = creature.take_damage(damage);
+            if hidden_from_players(creature, self.player_view) {
+                // The players see them get hit, not how hard or what they are weak to
+                taken.push(creature.name.clone());
+            } else if result.applied.is_empty() {
+                taken.push(format!("{} {}", creature.name, result.amount));
+            } else {
+                adjusted = true;
+                taken.push(format!(
+                    "{} {} ({})",
+                    creature.name,
+                    result.amount,
+                    result.applied.join(", ")
+                ));
+            }
+            if let Some(change) = result.health_change {
+                changes.push(format!("{} {}", creature.name, change));
+            }
+        }
+        if hits.len() > 1 || adjusted {
+            self.status = Some(format!("Damage taken: {}", taken.join(", ")));
+        }
+        self.report_health_changes(changes);
+    }
+
+    /// Adds who went down or got back up to the status, then clears out the defeated if asked to
+    fn report_health_changes(&mut self, changes: Vec<String>) {
+        if !changes.is_empty() {
+            let changes = changes.join(", ");
+            self.status = Some(match self.status.take() {
+                Some(status) => format!("{}. {}", status, changes),
+                None => changes,
+            });
+        }
+        if self.encounter.remove_defeated {
+            self.delete_creatures(self.encounter.defeated());
+        }
+    }
+
+    /// Deletes the creatures, keeping the selection on the hovered one if it survived
+    fn delete_creatures(&mut self, indices: Vec<usize>) {
+        if indices.is_empty() {
+            return;
+        }
+        // Last first so the indices still point at the right creatures
+        for index in indices.iter().rev() {
+            self.encounter.delete(*index);
+        }
+        self.marked = self
+            .marked
+            .iter()
+            .filter(|marked| !indices.contains(marked))
+            .map(|marked| marked - indices.iter().filter(|index| *index < marked).count())
+            .collect();
+        let selected = self
+            .selected_creature
+            .map(|selected| selected - indices.iter().filter(|index| **index < selected).count());
+        match selected {
+            _ if self.encounter.creatures.is_empty() => {
+                self.selected_creature = None;
+                self.text_area = new_text_area(vec![], &self.theme);
+            }
+            // Reselect to update notes, the last one if the end was deleted
+            Some(index) => self.select_creature(index.min(self.encounter.creatures.len() - 1)),
+            None => {}
+        }
+    }
+
+    /// Adds numbered copies of the hovered creature right below it
+    fn duplicate(&mut self, count: usize) {
+        let Some(index) = self.selected_creature else {
+            return;
+        };
+        let Some(copies) = self.encounter.duplicate(index, count, &mut self.rng) else {
+            self.status = Some(format!(
+                "Can't number {} more cop{} of {}",
+                count,
+                if count == 1 { "y" } else { "ies" },
+                self.encounter.creatures[index].name
+            ));
+            return;
+        };
+        self.marked = self
+            .marked
+            .iter()
+            .map(|marked| match *marked >= copies.start {
+                true => marked + copies.len(),
+                false => *marked,
+            })
+            .collect();
+
+        let names: Vec<&str> = copies
+            .map(|index| self.encounter.creatures[index].name.as_str())
+            .collect();
+        self.status = match names.as_slice() {
+            [] => None,
+            [name] => Some(format!("Added {}", name)),
+            [first, .., last] => Some(format!("Added {} to {}", first, last)),
+        };
+    }
+
+    /// Saves the hovered creature to the bestiary, over any template of the same name
+    fn save_template(&mut self) {
+        let Some(creature) = self.hovered_creature() else {
+            return;
+        };
+        if creature.name.is_empty() {
+            self.status = Some("Name the creature before saving it to the bestiary".into());
+            return;
+        }
+
+        let template = Template::from_creature(creature);
+        let name = template.name.clone();
+        let replaced = self.bestiary.insert(template);
+        if let Some(path) = &self.bestiary_path {
+            if let Err(err) = self.bestiary.write(path) {
+                info!("Failed to write bestiary {}: {}", path.display(), err);
+                self.status = Some(format!("Failed to save the bestiary: {}", err));
+                return;
+            }
+        }
+        self.status = Some(if replaced {
+            format!("Updated {} in the bestiary", name)
+        } else {
+            format!("Saved {} to the bestiary", name)
+        });
     }
 
     fn select_creature(&mut self, index: usize) {
@@ -526,56 +2167,231 @@ impl App<'_> {
                     .lines()
                     .map(|slice| slice.to_string())
                     .collect(),
+                &self.theme,
             );
             self.text_area
                 .move_cursor(CursorMove::Jump(row as u16, col as u16));
         }
     }
 
-    fn numeric_edit<T: Clone + Display + Default + FromStr>(
-        &mut self,
-        extract: impl Fn(&Creature) -> T,
-        update: impl Fn(&mut Creature, T),
-        revert: impl Fn(&mut Creature),
-        commit: impl Fn(&mut Creature),
-        ev: event::KeyEvent,
-    ) {
-        let Some(creature) = self
-            .selected_creature
-            .and_then(|index| self.creatures.get_mut(index))
-        else {
-            panic!("Editing an nonexistent")
+    fn expression_edit(&mut self, commit: impl Fn(&mut Creature, i32), ev: KeyEvent) {
+        if ev.code != KeyCode::Enter {
+            self.type_expression(ev);
+            return;
+        }
+
+        let Some(total) = self.roll_input() else {
+            return;
         };
+        // One roll for everyone, like a group initiative or a fireball
+        self.for_each_target(|creature| commit(creature, total));
+        self.input.clear();
+        self.mode = Mode::Normal;
+    }
 
-        let value = extract(creature);
+    /// Like expression_edit, for changes that can take a creature down or bring it back up
+    fn health_edit(&mut self, commit: impl Fn(&mut Creature, i32) -> Option<String>, ev: KeyEvent) {
+        if ev.code != KeyCode::Enter {
+            self.type_expression(ev);
+            return;
+        }
 
+        let Some(total) = self.roll_input() else {
+            return;
+        };
+        let changes = self
+            .targets()
+            .into_iter()
+            .filter_map(|index| {
+                let creature = &mut self.encounter.creatures[index];
+                commit(creature, total).map(|change| format!("{} {}", creature.name, change))
+            })
+            .collect();
+        self.input.clear();
+        self.mode = Mode::Normal;
+        self.report_health_changes(changes);
+    }
+
+    /// Rolls what was typed into App::input, empty counts as 0. None when it doesn't parse.
+    fn roll_input(&mut self) -> Option<i32> {
+        if self.input.trim().is_empty() {
+            return Some(0);
+        }
+
+        match Expression::parse(&self.input) {
+            Ok(expression) => {
+                let roll = expression.roll(&mut self.rng);
+                if !expression.is_constant() {
+                    info!("Rolled {}", roll);
+                    self.status = Some(roll.to_string());
+                }
+                Some(roll.total)
+            }
+            Err(err) => {
+                self.status = Some(format!("Can't roll '{}': {}", self.input, err));
+                None
+            }
+        }
+    }
+
+    /// Rolls the typed damage, empty counts as none. None when it doesn't parse.
+    fn roll_damage(&mut self) -> Option<Damage> {
+        if self.input.trim().is_empty() {
+            return Some(Damage::untyped(0));
+        }
+
+        match DamageExpression::parse(&self.input) {
+            Ok(expression) => {
+                let (damage, rolls) = expression.roll(&mut self.rng);
+                if !expression.is_constant() {
+                    let rolls: Vec<String> = rolls
+                        .iter()
+                        .zip(&damage.parts)
+                        .map(|(roll, (_, kind))| format!("{} {}", roll, kind).trim_end().into())
+                        .collect();
+                    info!("Rolled {}", rolls.join(", "));
+                    self.status = Some(rolls.join(", "));
+                }
+                Some(damage)
+            }
+            Err(err) => {
+                self.status = Some(format!("Can't roll '{}': {}", self.input, err));
+                None
+            }
+        }
+    }
+
+    /// Like a dice expression, with damage types and commas between them
+    fn type_damage(&mut self, ev: KeyEvent) {
+        match ev.code {
+            KeyCode::Char(ch) if ch.is_ascii_alphabetic() || ch == ',' => self.input.push(ch),
+            _ => self.type_expression(ev),
+        }
+    }
+
+    /// Typing and cancelling in the dice expression inputs
+    fn type_expression(&mut self, ev: KeyEvent) {
+        match ev.code {
+            KeyCode::Esc => {
+                self.input.clear();
+                self.mode = Mode::Normal;
+            }
+            KeyCode::Backspace => {
+                self.input.pop();
+            }
+            KeyCode::Char(ch) if ch.is_ascii_digit() || "dkhl+- ".contains(ch) => {
+                self.input.push(ch);
+            }
+
+            _ => {}
+        }
+    }
+
+    /// Armor class, fortitude, reflex, will and perception. Trailing ones can be left off to keep them.
+    fn defenses_edit(&mut self, ev: KeyEvent) {
         match ev.code {
             KeyCode::Enter => {
-                commit(creature);
+                let numbers: Result<Vec<i32>, _> =
+                    self.input.split_whitespace().map(str::parse).collect();
+                let numbers = match numbers {
+                    Ok(numbers) if numbers.len() <= 5 => numbers,
+                    _ => {
+                        self.status = Some(format!(
+                            "'{}' is not AC, Fort, Ref, Will and Perception",
+                            self.input.trim()
+                        ));
+                        return;
+                    }
+                };
+
+                if let Some(creature) = self.hovered_creature_mut() {
+                    let fields = [
+                        &mut creature.armor_class,
+                        &mut creature.saves.fortitude,
+                        &mut creature.saves.reflex,
+                        &mut creature.saves.will,
+                        &mut creature.perception,
+                    ];
+                    for (field, number) in fields.into_iter().zip(numbers) {
+                        *field = number;
+                    }
+                }
+                self.input.clear();
                 self.mode = Mode::Normal;
             }
             KeyCode::Esc => {
-                revert(creature);
+                self.input.clear();
                 self.mode = Mode::Normal;
             }
             KeyCode::Backspace => {
-                let mut old_amount = value.to_string();
-                old_amount.pop();
-                let new_amount = old_amount.parse().unwrap_or_default();
-                update(creature, new_amount);
+                self.input.pop();
+            }
+            KeyCode::Char(ch) if ch.is_ascii_digit() || "+- ".contains(ch) => {
+                self.input.push(ch);
             }
-            KeyCode::Char(ch) if ch.is_ascii_digit() => {
-                let mut old_amount = value.to_string();
-                old_amount.push(ch);
 
-                // If number won't fit, sets to zero
-                update(creature, old_amount.parse().unwrap_or_default());
+            _ => {}
+        }
+    }
+
+    fn adjustments_edit(&mut self, ev: KeyEvent) {
+        match ev.code {
+            KeyCode::Enter => {
+                let adjustments: Adjustments = match self.input.parse() {
+                    Ok(adjustments) => adjustments,
+                    Err(err) => {
+                        self.status = Some(err);
+                        return;
+                    }
+                };
+                self.for_each_target(|creature| creature.adjustments = adjustments.clone());
+                self.input.clear();
+                self.mode = Mode::Normal;
+            }
+            KeyCode::Esc => {
+                self.input.clear();
+                self.mode = Mode::Normal;
+            }
+            KeyCode::Backspace => {
+                self.input.pop();
             }
+            KeyCode::Char(ch) => self.input.push(ch),
 
             _ => {}
         }
     }
 
+    /// What to show in place of the value being edited
+    fn edited_cell(&self) -> Option<EditedCell> {
+        let creature = self.hovered_creature()?;
+        let input = &self.input;
+        let health = health_text(creature, self.player_view);
+        Some(match self.mode {
+            Mode::SetInitiative => EditedCell::Initiative(input.clone()),
+            Mode::SetInitiativeModifier => EditedCell::Initiative(format!("mod {}", input)),
+            // Only what's typed, without the numbers the players don't get to see
+            Mode::SetHealth | Mode::SetMaxHealth
+                if hidden_from_players(creature, self.player_view) =>
+            {
+                EditedCell::Health(input.clone())
+            }
+            Mode::SetTempHealth if hidden_from_players(creature, self.player_view) => {
+                EditedCell::Health(format!("+{}", input))
+            }
+            Mode::SetHealth if creature.max_health > 0 => {
+                EditedCell::Health(format!("{}/{}", input, creature.max_health))
+            }
+            Mode::SetHealth => EditedCell::Health(input.clone()),
+            Mode::SetMaxHealth => EditedCell::Health(format!("{}/{}", creature.health, input)),
+            Mode::SetTempHealth => EditedCell::Health(format!("{} (+{})", creature.health, input)),
+            Mode::BasicSave => EditedCell::Health(format!("{} -{}", health, input)),
+            Mode::HealthShift(shift) => {
+                EditedCell::Health(format!("{} {}{}", health, shift.sign(), input))
+            }
+            _ => return None,
+        })
+    }
+
     fn render_help(&mut self, area: Rect, buf: &mut Buffer) {
         let main_layout = Layout::default()
             .direction(Direction::Vertical)
@@ -595,15 +2411,16 @@ impl App<'_> {
                     vec![div]
                 }
             }
-            HotKey::Embed { pre, color, post } => vec![Line::from(vec![
-                format!("{pre}(").into(),
-                color.blue().bold(),
-                format!("){post}").into(),
-            ])],
             HotKey::Label { label, keys } => {
                 vec![Line::from(vec![
                     format!("{label}: ").into(),
-                    keys.blue().bold(),
+                    keys.set_style(self.theme.key),
+                ])]
+            }
+            HotKey::Bound { label, actions } => {
+                vec![Line::from(vec![
+                    format!("{label}: ").into(),
+                    self.keymap.describe(actions).set_style(self.theme.key),
                 ])]
             }
         }))
@@ -616,98 +2433,612 @@ impl App<'_> {
     }
 
     fn render_meta(&mut self, area: Rect, buf: &mut Buffer, selected_index: usize) {
-        let list = List::new(
-            vec!["Return to normal mode", "Quit"]
-                .iter()
-                .enumerate()
-                .map(|(index, option)| {
-                    if index == selected_index {
-                        Line::from(option.to_string()).blue()
-                    } else {
-                        Line::from(option.to_string())
-                    }
-                }),
-        )
-        .block(Block::default().title_bottom(self.mode.get_instructions().centered()));
+        let list = List::new(MetaOption::ALL.iter().enumerate().map(|(index, option)| {
+            if index == selected_index {
+                Line::from(option.label(self)).style(self.theme.accent)
+            } else {
+                Line::from(option.label(self))
+            }
+        }))
+        .block(
+            Block::default()
+                .title(self.status.clone().unwrap_or_default())
+                .title_bottom(
+                    self.mode
+                        .get_instructions(&self.keymap, &self.theme)
+                        .centered(),
+                ),
+        );
         Widget::render(list, area, buf);
     }
 
-    fn render_normal(&mut self, area: Rect, buf: &mut Buffer) {
+    fn render_recover(&mut self, area: Rect, buf: &mut Buffer) {
+        let creatures = self
+            .recovered
+            .as_ref()
+            .map(|save| save.encounter.creatures.len())
+            .unwrap_or_default();
+
+        let plural = if creatures == 1 { "" } else { "s" };
+        let question = match &self.save_path {
+            Some(path) => format!(
+                "Its autosave with {} creature{} is newer than {}, restore it?",
+                creatures,
+                plural,
+                path.display()
+            ),
+            None => format!(
+                "Its autosave has {} creature{}, restore it?",
+                creatures, plural
+            ),
+        };
+        let prompt = Paragraph::new(vec![
+            Line::from("The previous session did not exit cleanly."),
+            Line::from(question),
+        ])
+        .block(
+            Block::default().title_bottom(
+                self.mode
+                    .get_instructions(&self.keymap, &self.theme)
+                    .centered(),
+            ),
+        );
+        prompt.render(area, buf);
+    }
+
+    fn render_path_prompt(&mut self, area: Rect, buf: &mut Buffer, prompt: &str, path: &str) {
+        let prompt = Paragraph::new(Line::from(vec![
+            prompt.to_string().into(),
+            path.to_string().set_style(self.theme.accent),
+        ]))
+        .block(
+            Block::default()
+                .title(self.status.clone().unwrap_or_default())
+                .title_bottom(
+                    self.mode
+                        .get_instructions(&self.keymap, &self.theme)
+                        .centered(),
+                ),
+        );
+        prompt.render(area, buf);
+    }
+
+    /// Where everything goes in normal mode, for drawing and for working out what was clicked
+    fn normal_layout(&self, area: Rect) -> NormalLayout {
+        // Big fights scroll instead of pushing the notes off screen.
+        // Leaves room for the status line and the spacing around the notes.
+        let table_height = (self.shown().len() as u16 + 2)
+            .min(area.height.saturating_sub(3 + MIN_NOTES_HEIGHT))
+            .max(MIN_TABLE_HEIGHT);
         let main_layout = Layout::default()
             .direction(Direction::Vertical)
             .constraints(vec![
-                Constraint::Length((self.creatures.len() + 2) as u16),
+                Constraint::Length(table_height),
                 Constraint::Fill(1),
+                Constraint::Length(1),
             ])
             .spacing(1)
             .split(area);
 
+        let (initiative_width, edited_health_width) = match self.edited_cell() {
+            Some(EditedCell::Initiative(text)) => (text.chars().count() as u16, 0),
+            Some(EditedCell::Health(text)) => (0, text.chars().count() as u16),
+            None => (0, 0),
+        };
+        let initiative_width = initiative_width.max(3);
+        let health_width = self
+            .encounter
+            .creatures
+            .iter()
+            .map(|creature| health_text(creature, self.player_view).chars().count() as u16)
+            .max()
+            .unwrap_or_default()
+            .max(edited_health_width)
+            .max(6);
+
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(vec![
+                Constraint::Length(initiative_width),   // Initiative
+                Constraint::Fill(1),                    // Name
+                Constraint::Length(health_width),       // Health
+                Constraint::Length(HEALTH_GAUGE_WIDTH), // Health gauge
+                Constraint::Fill(2),                    // Statuses
+            ])
+            .spacing(1)
+            .split(Block::bordered().inner(main_layout[0]));
+
+        NormalLayout {
+            table: main_layout[0],
+            columns,
+            notes: main_layout[1],
+            status: main_layout[2],
+        }
+    }
+
+    fn render_normal(&mut self, area: Rect, buf: &mut Buffer) {
+        let layout = self.normal_layout(area);
+
         let (table_border, table_border_color, notes_border, notes_border_color) =
             if self.mode == Mode::EditNotes {
                 (
                     border::PLAIN,
                     Style::default(),
                     border::DOUBLE,
-                    Style::default().blue(),
+                    self.theme.accent,
                 )
             } else {
                 (
                     border::DOUBLE,
-                    Style::default().blue(),
+                    self.theme.accent,
                     border::PLAIN,
                     Style::default(),
                 )
             };
 
         // Creature table
+        let mut table_title = if self.encounter.round == 0 {
+            " Creatures ".to_string()
+        } else {
+            format!(" Creatures - Round {} ", self.encounter.round)
+        };
+        if let Some(side) = self.side_filter {
+            table_title = format!("{}- {} only ", table_title, side.name());
+        }
+        let marked = self.marked_count();
+        if marked > 0 {
+            table_title = format!("{}- {} marked ", table_title, marked);
+        }
+        if self.player_view {
+            table_title = format!("{}- player view ", table_title);
+        }
         let table_block = Block::bordered()
-            .title(Line::from(" Creatures ".bold()).centered())
+            .title(Line::from(table_title.bold()).centered())
             .border_set(table_border)
             .border_style(table_border_color);
 
-        let table_layout = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints(vec![
-                Constraint::Length(3),  // Initiative
-                Constraint::Fill(1),    // Name
-                Constraint::Length(10), // Health
-                Constraint::Fill(2),    // Statuses
-            ])
-            .spacing(1)
-            .split(table_block.inner(main_layout[0]));
-        table_block.render(main_layout[0], buf);
+        table_block.render(layout.table, buf);
 
-        let (initiative_list, name_list, health_list) = self
-            .creatures
+        let edited_cell = self.edited_cell();
+        let visible_rows = layout.visible_rows();
+        let shown = self.shown();
+        let mut columns: Vec<Vec<ListItem>> = vec![vec![]; layout.columns.len()];
+        for index in shown
             .iter()
-            .enumerate()
-            .map(|(index, creature)| creature.render(index, self.selected_creature))
-            .collect::<(Vec<ListItem>, Vec<ListItem>, Vec<ListItem>)>();
-
-        for (column, items) in [initiative_list, name_list, health_list]
-            .into_iter()
-            .enumerate()
+            .copied()
+            .skip(self.table_offset)
+            .take(visible_rows)
         {
+            let edited = edited_cell
+                .as_ref()
+                .filter(|_| self.selected_creature == Some(index));
+            let cells = creature_row(
+                &self.encounter.creatures[index],
+                self.selected_creature == Some(index),
+                self.encounter.active_creature == Some(index),
+                self.marked.contains(&index),
+                edited,
+                self.player_view,
+                &self.theme,
+            );
+            for (column, cell) in cells.into_iter().enumerate() {
+                columns[column].push(cell);
+            }
+        }
+
+        for (column, items) in columns.into_iter().enumerate() {
             let list = List::new(items);
-            Widget::render(list, table_layout[column], buf);
+            Widget::render(list, layout.columns[column], buf);
         }
 
-        // Notes of selected creature
+        let creatures = shown.len();
+        if creatures > visible_rows {
+            // One position per possible offset, so the thumb reaches the bottom with the last row
+            let mut state = ScrollbarState::new(creatures - visible_rows + 1)
+                .viewport_content_length(visible_rows)
+                .position(self.table_offset);
+            StatefulWidget::render(
+                Scrollbar::new(ScrollbarOrientation::VerticalRight).style(table_border_color),
+                layout.table.inner(Margin::new(0, 1)),
+                buf,
+                &mut state,
+            );
+        }
+
+        if let Mode::Conditions(index) = self.mode {
+            self.render_condition_picker(layout.notes, buf, index);
+        } else {
+            self.render_notes(layout.notes, buf, notes_border, notes_border_color);
+        }
+
+        if let Some(status) = &self.status {
+            Paragraph::new(status.as_str()).render(layout.status, buf);
+        }
+    }
+
+    fn render_notes(
+        &mut self,
+        area: Rect,
+        buf: &mut Buffer,
+        border: border::Set,
+        border_color: Style,
+    ) {
+        // Players only get to see the notes of their own side
+        let hidden = self
+            .hovered_creature()
+            .is_some_and(|creature| hidden_from_players(creature, self.player_view));
+        // Notes of selected creature, topped with its defenses when known
+        let title = match (&self.mode, self.hovered_creature()) {
+            (Mode::SetDefenses, _) => format!(" AC Fort Ref Will Perception: {} ", self.input),
+            (Mode::Copies, Some(creature)) => {
+                format!(" Copies of {}: {} ", creature.name, self.input)
+            }
+            (_, Some(creature)) if creature.armor_class > 0 && !hidden => format!(
+                " Notes - AC {}, {}, Perception {:+} ",
+                creature.armor_class, creature.saves, creature.perception
+            ),
+            _ => " Notes ".into(),
+        };
         let note_block = Block::bordered()
-            .title(Line::from(" Notes ".bold()).centered())
-            .title_bottom(self.mode.get_instructions().centered())
-            .border_set(notes_border)
-            .border_style(notes_border_color);
-        self.text_area.render(note_block.inner(main_layout[1]), buf);
-        note_block.render(main_layout[1], buf);
+            .title(Line::from(title.bold()).centered())
+            .title_bottom(
+                self.mode
+                    .get_instructions(&self.keymap, &self.theme)
+                    .centered(),
+            )
+            .border_set(border)
+            .border_style(border_color);
+
+        // Immunities, weaknesses and resistances get a line of their own above the notes
+        let adjustments = match (&self.mode, self.hovered_creature()) {
+            (Mode::SetAdjustments, _) => Some(Line::from(vec![
+                "Immune, weak, resist: ".set_style(self.theme.label),
+                self.input.clone().set_style(self.theme.accent),
+            ])),
+            (_, Some(creature)) if !creature.adjustments.is_empty() && !hidden => {
+                Some(Line::from(creature.adjustments.to_string()).style(self.theme.muted))
+            }
+            _ => None,
+        };
+        let mut notes_area = note_block.inner(area);
+        if let Some(line) = adjustments {
+            let [line_area, rest] =
+                Layout::vertical([Constraint::Length(1), Constraint::Fill(1)]).areas(notes_area);
+            Paragraph::new(line).render(line_area, buf);
+            notes_area = rest;
+        }
+        if !hidden || self.mode == Mode::EditNotes {
+            self.text_area.render(notes_area, buf);
+        }
+        note_block.render(area, buf);
+    }
+
+    fn render_condition_picker(&mut self, area: Rect, buf: &mut Buffer, selected_index: usize) {
+        let current = self
+            .hovered_creature()
+            .map(|creature| creature.conditions.clone())
+            .unwrap_or_default();
+
+        let title = match self.marked_count() {
+            0 => " Conditions ".to_string(),
+            marked => format!(" Conditions - changes apply to {} marked ", marked),
+        };
+
+        let list = List::new(ConditionKind::ALL.iter().map(|kind| {
+            match current.iter().find(|condition| condition.kind == *kind) {
+                Some(condition) => Line::from(vec![
+                    condition.to_string().bold(),
+                    format!(" ({})", condition.rule.describe()).into(),
+                ]),
+                None => Line::from(kind.name()).style(self.theme.muted),
+            }
+        }))
+        .highlight_style(self.theme.accent)
+        .block(
+            Block::bordered()
+                .title(Line::from(title.bold()).centered())
+                .title_bottom(
+                    self.mode
+                        .get_instructions(&self.keymap, &self.theme)
+                        .centered(),
+                )
+                .border_set(border::DOUBLE)
+                .border_style(self.theme.accent),
+        );
+
+        let mut state = ListState::default().with_selected(Some(selected_index));
+        StatefulWidget::render(list, area, buf, &mut state);
+    }
+
+    fn render_turn_summary(&mut self, area: Rect, buf: &mut Buffer) {
+        let popup = popup_area(area, 60, self.turn_summary.len() as u16 + 2);
+        Clear.render(popup, buf);
+        Paragraph::new(
+            self.turn_summary
+                .iter()
+                .map(|line| Line::from(line.as_str()))
+                .collect::<Vec<_>>(),
+        )
+        .wrap(Wrap { trim: false })
+        .block(
+            Block::bordered()
+                .title(Line::from(" End of turn ".bold()).centered())
+                .title_bottom(
+                    self.mode
+                        .get_instructions(&self.keymap, &self.theme)
+                        .centered(),
+                )
+                .border_set(border::DOUBLE)
+                .border_style(self.theme.accent),
+        )
+        .render(popup, buf);
+    }
+
+    fn render_save_dc(&mut self, area: Rect, buf: &mut Buffer, damage: &Damage, save: SaveKind) {
+        let popup = popup_area(area, 60, 5);
+        Clear.render(popup, buf);
+
+        let saves: Vec<Span> = SaveKind::ALL
+            .iter()
+            .flat_map(|kind| {
+                let style = if *kind == save {
+                    self.theme.selected
+                } else {
+                    self.theme.text
+                };
+                [kind.to_string().set_style(style), " ".into()]
+            })
+            .collect();
+        Paragraph::new(vec![
+            Line::from([vec!["Save: ".into()], saves].concat()),
+            Line::from(vec![
+                "DC: ".into(),
+                self.input.clone().set_style(self.theme.accent),
+            ]),
+            Line::from("Leave the DC empty to pick outcomes by hand").style(self.theme.muted),
+        ])
+        .block(
+            Block::bordered()
+                .title(Line::from(format!(" {} damage, basic save ", damage).bold()).centered())
+                .border_set(border::DOUBLE)
+                .border_style(self.theme.accent),
+        )
+        .render(popup, buf);
+    }
+
+    fn render_save_outcomes(
+        &mut self,
+        area: Rect,
+        buf: &mut Buffer,
+        damage: &Damage,
+        save: Option<(SaveKind, i32)>,
+        outcomes: &[SaveOutcome],
+        selection: usize,
+    ) {
+        // Border, a line for the keys and a row per creature.
+        // Wider than the other popups to fit the rolls and damage on 80 columns.
+        let rows = (outcomes.len() as u16).min(POPUP_ROWS);
+        let popup = popup_area(area, 80, rows + 3);
+        Clear.render(popup, buf);
+
+        let title = match save {
+            Some((save, dc)) => format!(" {} damage, DC {} basic {} ", damage, dc, save),
+            None => format!(" {} damage, basic save ", damage),
+        };
+        let block = Block::bordered()
+            .title(Line::from(title.bold()).centered())
+            .border_set(border::DOUBLE)
+            .border_style(self.theme.accent);
+        let [legend, results] = Layout::vertical([Constraint::Length(1), Constraint::Fill(1)])
+            .areas(block.inner(popup));
+        block.render(popup, buf);
+
+        Line::from("1 crit fail, 2 fail, 3 success, 4 crit success")
+            .style(self.theme.muted)
+            .render(legend, buf);
+
+        let list = List::new(outcomes.iter().map(|outcome| {
+            // What the creature would take after its weaknesses and resistances
+            let (name, taken, hidden) = self
+                .encounter
+                .creatures
+                .get(outcome.index)
+                .map(|creature| {
+                    let damage = damage.scaled(|amount| outcome.degree.basic_damage(amount));
+                    (
+                        creature.name.clone(),
+                        creature.adjustments.apply(&damage).0,
+                        hidden_from_players(creature, self.player_view),
+                    )
+                })
+                .unwrap_or_default();
+            // The players see how it went, not the bonus or the damage that got through
+            if hidden {
+                return Line::from(format!("{:<16} {:<11} {}", name, "", outcome.degree));
+            }
+            let check = outcome
+                .check
+                .map(|check| check.to_string())
+                .unwrap_or_default();
+            Line::from(format!(
+                "{:<16} {:<11} {:<17} -{}",
+                name,
+                check,
+                outcome.degree.to_string(),
+                taken
+            ))
+        }))
+        .highlight_style(self.theme.selected);
+        let mut state = ListState::default().with_selected(Some(selection));
+        StatefulWidget::render(list, results, buf, &mut state);
+    }
+
+    fn render_recovery_check(
+        &mut self,
+        area: Rect,
+        buf: &mut Buffer,
+        creature: usize,
+        check: Check,
+        degree: Degree,
+    ) {
+        let popup = popup_area(area, 60, 5);
+        Clear.render(popup, buf);
+
+        let Some(creature) = self.encounter.creatures.get(creature) else {
+            return;
+        };
+        let degrees: Vec<Span> = Degree::ALL
+            .iter()
+            .flat_map(|option| {
+                let style = if *option == degree {
+                    self.theme.selected
+                } else {
+                    self.theme.text
+                };
+                [option.to_string().set_style(style), " ".into()]
+            })
+            .collect();
+        Paragraph::new(vec![
+            Line::from(format!(
+                "DC {} flat check, rolled {}",
+                creature.recovery_dc(),
+                check
+            )),
+            Line::from(degrees),
+            Line::from("Pick what the player rolled if they roll their own")
+                .style(self.theme.muted),
+        ])
+        .block(
+            Block::bordered()
+                .title(
+                    Line::from(format!(" {}'s recovery check ", creature.name).bold()).centered(),
+                )
+                .border_set(border::DOUBLE)
+                .border_style(self.theme.accent),
+        )
+        .render(popup, buf);
+    }
+
+    fn render_persistent_damage(
+        &mut self,
+        area: Rect,
+        buf: &mut Buffer,
+        creature: usize,
+        ticks: &[PersistentTick],
+        selection: usize,
+    ) {
+        // Border, a line explaining the check and a row per persistent damage
+        let rows = (ticks.len() as u16).min(POPUP_ROWS);
+        let popup = popup_area(area, 80, rows + 3);
+        Clear.render(popup, buf);
+
+        let name = self
+            .encounter
+            .creatures
+            .get(creature)
+            .map(|creature| creature.name.as_str())
+            .unwrap_or_default();
+        let block = Block::bordered()
+            .title(Line::from(format!(" End of {}'s turn ", name).bold()).centered())
+            .border_set(border::DOUBLE)
+            .border_style(self.theme.accent);
+        let [legend, rows_area] = Layout::vertical([Constraint::Length(1), Constraint::Fill(1)])
+            .areas(block.inner(popup));
+        block.render(popup, buf);
+        Line::from(format!(
+            "Persistent damage, DC {} flat check to end it",
+            PersistentDamage::FLAT_CHECK_DC
+        ))
+        .style(self.theme.muted)
+        .render(legend, buf);
+
+        let list = List::new(ticks.iter().map(|tick| {
+            Line::from(format!(
+                "{:<22} {:>3} damage  flat {:<11} {}",
+                tick.persistent.to_string(),
+                tick.damage.total(),
+                tick.check.to_string(),
+                if tick.ends { "ends" } else { "continues" }
+            ))
+        }))
+        .highlight_style(self.theme.selected);
+        let mut state = ListState::default().with_selected(Some(selection));
+        StatefulWidget::render(list, rows_area, buf, &mut state);
+    }
+
+    fn render_bestiary(&mut self, area: Rect, buf: &mut Buffer, query: &str, selection: usize) {
+        let matches = self.bestiary.search(query);
+        // Border, search line and at least one row for the matches
+        let rows = (matches.len() as u16).clamp(1, POPUP_ROWS);
+        let popup = popup_area(area, 60, rows + 3);
+        Clear.render(popup, buf);
+
+        let block = Block::bordered()
+            .title(Line::from(" Bestiary ".bold()).centered())
+            .border_set(border::DOUBLE)
+            .border_style(self.theme.accent);
+        let [search, results] = Layout::vertical([Constraint::Length(1), Constraint::Fill(1)])
+            .areas(block.inner(popup));
+        block.render(popup, buf);
+
+        Line::from(vec![
+            "Search: ".into(),
+            query.to_string().set_style(self.theme.accent),
+        ])
+        .render(search, buf);
+
+        if matches.is_empty() {
+            Line::from("No matches")
+                .style(self.theme.muted)
+                .render(results, buf);
+            return;
+        }
+        let list = List::new(matches.iter().map(|index| {
+            let template = &self.bestiary.templates[*index];
+            let mut stats = vec![];
+            if template.max_health > 0 {
+                stats.push(format!("HP {}", template.max_health));
+            }
+            if template.armor_class > 0 {
+                stats.push(format!("AC {}", template.armor_class));
+            }
+            Line::from(vec![
+                template.name.clone().into(),
+                format!(" {}", stats.join(", ")).set_style(self.theme.muted),
+            ])
+        }))
+        .highlight_style(self.theme.selected);
+        let mut state = ListState::default().with_selected(Some(selection));
+        StatefulWidget::render(list, results, buf, &mut state);
     }
 }
 
-fn new_text_area<'a>(lines: Vec<String>) -> TextArea<'a> {
+// Most rows the bestiary and save outcome popups show at once, the rest scroll
+const POPUP_ROWS: u16 = 10;
+
+fn yes_no(value: bool) -> &'static str {
+    if value {
+        "yes"
+    } else {
+        "no"
+    }
+}
+
+fn popup_area(area: Rect, width_percent: u16, height: u16) -> Rect {
+    let [popup] = Layout::horizontal([Constraint::Percentage(width_percent)])
+        .flex(layout::Flex::Center)
+        .areas(area);
+    let [popup] = Layout::vertical([Constraint::Length(height)])
+        .flex(layout::Flex::Center)
+        .areas(popup);
+    popup
+}
+
+fn new_text_area<'a>(lines: Vec<String>, theme: &Theme) -> TextArea<'a> {
     // Centralized here so we can add stuff like line numbers
     // We need to recreate it occasionally because you can't set the content after creation
     let mut ta = TextArea::new(lines);
-    ta.set_line_number_style(Style::default().bg(Color::DarkGray));
+    ta.set_line_number_style(theme.line_numbers);
     ta.set_cursor_style(Style::default()); // No underline on cursor line, doesn't work
 
     ta
@@ -715,100 +3046,237 @@ fn new_text_area<'a>(lines: Vec<String>) -> TextArea<'a> {
 
 impl Widget for App<'_> {
     fn render(mut self, area: Rect, buf: &mut Buffer) {
-        match self.mode {
+        match self.mode.clone() {
             Mode::Help => self.render_help(area, buf),
+            Mode::Recover => self.render_recover(area, buf),
             Mode::Meta(index) => self.render_meta(area, buf, index),
+            Mode::SaveAs(path) => self.render_path_prompt(area, buf, "Save encounter to: ", &path),
+            Mode::Import(path) => {
+                self.render_path_prompt(area, buf, "Import creature from: ", &path)
+            }
+            Mode::TurnSummary => {
+                self.render_normal(area, buf);
+                self.render_turn_summary(area, buf);
+            }
+            Mode::SaveDc { damage, save, .. } => {
+                self.render_normal(area, buf);
+                self.render_save_dc(area, buf, &damage, save);
+            }
+            Mode::RecoveryCheck {
+                creature,
+                check,
+                degree,
+            } => {
+                self.render_normal(area, buf);
+                self.render_recovery_check(area, buf, creature, check, degree);
+            }
+            Mode::PersistentDamage {
+                creature,
+                ticks,
+                selection,
+            } => {
+                self.render_normal(area, buf);
+                self.render_persistent_damage(area, buf, creature, &ticks, selection);
+            }
+            Mode::SaveOutcomes {
+                damage,
+                save,
+                outcomes,
+                selection,
+            } => {
+                self.render_normal(area, buf);
+                self.render_save_outcomes(area, buf, &damage, save, &outcomes, selection);
+            }
+            Mode::Bestiary { query, selection } => {
+                self.render_normal(area, buf);
+                self.render_bestiary(area, buf, &query, selection);
+            }
             _ => self.render_normal(area, buf),
         }
     }
 }
 
-#[derive(Debug, Clone, Copy)]
-enum HealthShift {
-    Increase(u32),
-    Decrease(u32),
+/// Degree of success an outcome key picks
+fn picked_degree(action: Action) -> Option<Degree> {
+    OUTCOMES
+        .iter()
+        .position(|outcome| *outcome == action)
+        .map(|index| Degree::ALL[index])
 }
 
-impl FromStr for HealthShift {
-    type Err = <i32 as FromStr>::Err;
+/// Like `1/2/3/4 Left/Right`
+fn outcome_keys(keymap: &Keymap) -> String {
+    format!(
+        "{} {}",
+        keymap.describe(OUTCOMES),
+        keymap.describe(&[Action::WorseOutcome, Action::BetterOutcome])
+    )
+}
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let numeric: i32 = s.parse()?;
+const HEALTH_GAUGE_WIDTH: u16 = 10;
+// More copies than this at once is more likely a typo than a horde
+const MAX_COPIES: i32 = 100;
+// Creature table with one row showing and a notes pane with a few lines, borders included
+const MIN_TABLE_HEIGHT: u16 = 3;
+const MIN_NOTES_HEIGHT: u16 = 5;
+const DOUBLE_CLICK_TIME: Duration = Duration::from_millis(500);
 
-        Ok(if numeric.is_positive() {
-            HealthShift::Increase(numeric.try_into().unwrap())
-        } else {
-            HealthShift::Decrease((-numeric).try_into().unwrap())
-        })
-    }
+struct NormalLayout {
+    table: Rect,
+    // Inside the table border: initiative, name, health, health gauge, statuses
+    columns: Rc<[Rect]>,
+    notes: Rect,
+    status: Rect,
 }
 
-impl Display for HealthShift {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let (sign_char, magnitude) = match self {
-            HealthShift::Increase(mag) => ('+', mag),
-            HealthShift::Decrease(mag) => ('-', mag),
-        };
-        write!(f, "{}{}", sign_char, magnitude)
+impl NormalLayout {
+    fn visible_rows(&self) -> usize {
+        self.columns[0].height as usize
     }
 }
 
-#[derive(Debug, Clone)]
-struct Creature {
-    name: String,
-    health: i32,
-    health_shift: Option<HealthShift>,
-    initiative: i32,
-    notes: String,
-    notes_cursor_pos: (usize, usize),
+// How one creature did against basic save damage
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SaveOutcome {
+    index: usize,
+    degree: Degree,
+    // None when the outcome was picked by hand
+    check: Option<Check>,
 }
 
-impl Creature {
-    fn render(
-        &self,
-        index: usize,
-        selected_index: Option<usize>,
-    ) -> (ListItem, ListItem, ListItem) {
-        let selected = selected_index == Some(index);
-
-        // Inverse colors when selected
-        let (fg_color, bg_color) = if selected {
-            (Color::Black, Color::White)
-        } else {
-            (Color::White, Color::Black)
-        };
+// Table cell that is being typed into, replaces the value while editing
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum EditedCell {
+    Initiative(String),
+    Health(String),
+}
 
-        let name = if self.name.is_empty() {
-            "<empty>".into()
-        } else {
-            self.name.clone()
-        };
+fn health_gauge<'a>(creature: &Creature, theme: &Theme) -> Line<'a> {
+    if creature.max_health <= 0 {
+        return Line::default();
+    }
 
-        let health = if let Some(health_shift) = self.health_shift {
-            format!("{} {}", self.health, health_shift)
-        } else {
-            self.health.to_string()
-        };
+    let ratio = creature.health.clamp(0, creature.max_health) as f64 / creature.max_health as f64;
+    let filled = (ratio * HEALTH_GAUGE_WIDTH as f64).round() as usize;
+    // Bloodied at half, in real trouble at a quarter
+    let style = if ratio > 0.5 {
+        theme.healthy
+    } else if ratio > 0.25 {
+        theme.bloodied
+    } else {
+        theme.critical
+    };
 
-        (
-            ListItem::from(self.initiative.to_string())
-                .fg(fg_color)
-                .bg(bg_color),
-            ListItem::from(name).fg(fg_color).bg(bg_color),
-            ListItem::from(health).fg(fg_color).bg(bg_color),
-        )
+    Line::from(vec![
+        "█".repeat(filled).set_style(style),
+        "░"
+            .repeat(HEALTH_GAUGE_WIDTH as usize - filled)
+            .set_style(theme.muted),
+    ])
+}
+
+/// Whether the players shouldn't see the numbers and notes of the creature
+fn hidden_from_players(creature: &Creature, player_view: bool) -> bool {
+    player_view && !creature.side.is_friendly()
+}
+
+/// Health as numbers, or as a word when the players shouldn't know them
+fn health_text(creature: &Creature, player_view: bool) -> String {
+    if hidden_from_players(creature, player_view) {
+        creature.health_description().into()
+    } else {
+        creature.health_text()
     }
 }
 
-impl Default for Creature {
-    fn default() -> Self {
-        Creature {
-            name: "".into(),
-            health: 0,
-            health_shift: None,
-            initiative: 0,
-            notes: "".into(),
-            notes_cursor_pos: (0, 0),
+fn creature_row<'a>(
+    creature: &'a Creature,
+    selected: bool,
+    active: bool,
+    marked: bool,
+    edited: Option<&EditedCell>,
+    player_view: bool,
+    theme: &Theme,
+) -> [ListItem<'a>; 5] {
+    let mut style = if selected { theme.selected } else { theme.text };
+    // Out of the fight, still there to be looted or brought back
+    if creature.dead {
+        style = style.patch(theme.muted);
+    }
+
+    let mut name = if creature.name.is_empty() {
+        "<empty>".into()
+    } else {
+        creature.name.clone()
+    };
+    // Tagged as well as colored, for themes without colors
+    match creature.side {
+        Side::Enemy => {}
+        Side::Party => name = format!("{} (PC)", name),
+        Side::Ally => name = format!("{} (ally)", name),
+        Side::Neutral => name = format!("{} (neutral)", name),
+    }
+    if marked {
+        name = format!("● {}", name);
+    }
+    if active {
+        name = format!("▶ {}", name);
+    }
+
+    let death = match (creature.dead, creature.side) {
+        (false, _) => None,
+        (true, Side::Party) => Some("Dead".to_string()),
+        (true, _) => Some("Defeated".to_string()),
+    };
+    let statuses = death
+        .into_iter()
+        .chain(
+            creature
+                .conditions
+                .iter()
+                .map(|condition| condition.to_string()),
+        )
+        .chain(creature.persistent.iter().map(PersistentDamage::to_string))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    // The creature whose turn it is stands out even when not selected
+    let mut name_style = style;
+    if !selected && !creature.dead {
+        name_style = name_style.patch(theme.side(creature.side));
+    }
+    if active {
+        style = style.add_modifier(Modifier::BOLD);
+        name_style = style;
+        if !selected {
+            name_style = name_style.patch(theme.active);
         }
     }
+
+    let initiative = creature
+        .initiative
+        .map(|initiative| initiative.to_string())
+        .unwrap_or("-".into());
+    let health_hidden = hidden_from_players(creature, player_view);
+    let (initiative, health) = match edited {
+        Some(EditedCell::Initiative(text)) => (text.clone(), health_text(creature, player_view)),
+        Some(EditedCell::Health(text)) => (initiative, text.clone()),
+        None => (initiative, health_text(creature, player_view)),
+    };
+    let gauge = if health_hidden {
+        Line::default()
+    } else {
+        health_gauge(creature, theme)
+    };
+
+    [
+        ListItem::from(initiative).style(style),
+        ListItem::from(name).style(name_style),
+        ListItem::from(health).style(style),
+        ListItem::from(gauge).style(style),
+        ListItem::from(statuses).style(style),
+    ]
 }
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,1215 @@
+use ratatui::{
+    backend::TestBackend,
+    crossterm::event::{
+        Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+    },
+    Terminal,
+};
+
+use super::*;
+use crate::theme::ThemeName;
+
+const WIDTH: u16 = 80;
+const HEIGHT: u16 = 20;
+
+// Drives the app like the main loop does, but with scripted keys and an in-memory terminal
+struct Harness {
+    app: App<'static>,
+    terminal: Terminal<TestBackend>,
+}
+
+impl Harness {
+    fn new(init_test_creatures: bool) -> Self {
+        Harness::from_app(App::new(init_test_creatures))
+    }
+
+    fn from_app(app: App<'static>) -> Self {
+        let mut app = app.with_seed(0);
+        app.handle_event(Event::Resize(WIDTH, HEIGHT));
+        Harness {
+            app,
+            terminal: Terminal::new(TestBackend::new(WIDTH, HEIGHT)).unwrap(),
+        }
+    }
+
+    fn press(&mut self, code: KeyCode) -> &mut Self {
+        self.app
+            .handle_event(Event::Key(KeyEvent::new(code, KeyModifiers::NONE)));
+        self
+    }
+
+    fn mouse(&mut self, kind: MouseEventKind, column: u16, row: u16) -> &mut Self {
+        self.app.handle_event(Event::Mouse(MouseEvent {
+            kind,
+            column,
+            row,
+            modifiers: KeyModifiers::NONE,
+        }));
+        self
+    }
+
+    fn click(&mut self, column: u16, row: u16) -> &mut Self {
+        self.mouse(MouseEventKind::Down(MouseButton::Left), column, row)
+    }
+
+    /// Presses every character in order, for typing and single letter commands alike
+    fn keys(&mut self, keys: &str) -> &mut Self {
+        for ch in keys.chars() {
+            self.press(KeyCode::Char(ch));
+        }
+        self
+    }
+
+    fn buffer(&mut self) -> Buffer {
+        let app = self.app.clone();
+        self.terminal
+            .draw(|frame| frame.render_widget(app, frame.area()))
+            .unwrap();
+        self.terminal.backend().buffer().clone()
+    }
+
+    fn screen(&mut self) -> Vec<String> {
+        let buffer = self.buffer();
+        buffer
+            .content
+            .chunks(buffer.area.width as usize)
+            .map(|row| row.iter().map(|cell| cell.symbol()).collect())
+            .collect()
+    }
+
+    fn screen_contains(&mut self, text: &str) -> bool {
+        self.screen().iter().any(|line| line.contains(text))
+    }
+
+    /// Screen row the text first shows up on
+    fn row_of(&mut self, text: &str) -> usize {
+        self.screen()
+            .iter()
+            .position(|line| line.contains(text))
+            .unwrap_or_else(|| panic!("'{}' is not on screen", text))
+    }
+
+    fn creature(&self, index: usize) -> &Creature {
+        &self.app.encounter.creatures[index]
+    }
+}
+
+#[test]
+fn normal_mode_snapshot() {
+    let mut harness = Harness::new(true);
+    let expected = [
+        "╔═════════════════════════════════ Creatures ══════════════════════════════════╗",
+        "║-   Goblin             5                                                      ║",
+        "║-   Chodlin            4                                                      ║",
+        "║-   Boblin             4                                                      ║",
+        "╚══════════════════════════════════════════════════════════════════════════════╝",
+        "                                                                                ",
+        "┌─────────────────────────────────── Notes ────────────────────────────────────┐",
+        "│ 1 Very gobliny                                                               │",
+        "│                                                                              │",
+        "│                                                                              │",
+        "│                                                                              │",
+        "│                                                                              │",
+        "│                                                                              │",
+        "│                                                                              │",
+        "│                                                                              │",
+        "│                                                                              │",
+        "│                                                                              │",
+        "└───────────────────────────── Exit: Esc Help: ? ──────────────────────────────┘",
+        "                                                                                ",
+        "                                                                                ",
+    ];
+    assert_eq!(harness.screen(), expected);
+}
+
+#[test]
+fn navigation_moves_the_notes_along() {
+    let mut harness = Harness::new(true);
+    harness.keys("j");
+    assert_eq!(harness.app.selected_creature, Some(1));
+    assert!(harness.screen_contains("Cousin of Boblin"));
+
+    harness.keys("k").keys("k");
+    assert_eq!(harness.app.selected_creature, Some(2));
+    assert!(harness.screen_contains("The goblin"));
+}
+
+#[test]
+fn rename_confirms_and_cancels() {
+    let mut harness = Harness::new(true);
+    harness.keys("r");
+    for _ in 0.."Goblin".len() {
+        harness.press(KeyCode::Backspace);
+    }
+    harness.keys("Hobgoblin").press(KeyCode::Enter);
+    assert_eq!(harness.creature(0).name, "Hobgoblin");
+    assert!(harness.screen_contains("Hobgoblin"));
+
+    harness.keys("rxyz").press(KeyCode::Esc);
+    assert_eq!(harness.creature(0).name, "Hobgoblin");
+    assert_eq!(harness.app.mode, Mode::Normal);
+}
+
+#[test]
+fn rename_without_creatures_does_not_panic() {
+    let mut harness = Harness::new(false);
+    harness.keys("r");
+    assert_eq!(harness.app.mode, Mode::Normal);
+
+    // Got into rename mode some other way
+    harness.app.mode = Mode::Rename(String::new());
+    harness.keys("x");
+    assert_eq!(harness.app.mode, Mode::Normal);
+    assert!(harness.screen_contains(" Creatures "));
+}
+
+#[test]
+fn added_creature_is_renamed_right_away() {
+    let mut harness = Harness::new(false);
+    harness.keys("aOrc").press(KeyCode::Enter);
+    assert_eq!(harness.app.encounter.creatures.len(), 1);
+    assert_eq!(harness.creature(0).name, "Orc");
+    assert!(harness.screen_contains("Orc"));
+}
+
+#[test]
+fn set_health_shows_the_input_while_typing() {
+    let mut harness = Harness::new(true);
+    harness.keys("h");
+    for _ in 0..3 {
+        harness.press(KeyCode::Backspace);
+    }
+    harness.keys("12");
+    assert!(harness.screen()[1].contains("12"));
+    assert!(harness.screen_contains("Confirm: Enter, Cancel: Esc"));
+    assert_eq!(harness.creature(0).health, 5);
+
+    harness.press(KeyCode::Enter);
+    assert_eq!(harness.creature(0).health, 12);
+    assert_eq!(harness.app.mode, Mode::Normal);
+}
+
+#[test]
+fn set_health_rolls_dice() {
+    let mut harness = Harness::new(true);
+    harness.keys("h").press(KeyCode::Backspace).keys("2d6");
+    harness.press(KeyCode::Enter);
+
+    let health = harness.creature(0).health;
+    assert!((2..=12).contains(&health));
+    assert!(harness.screen_contains("2d6: ["));
+    assert!(harness.screen_contains(&format!("] = {}", health)));
+}
+
+#[test]
+fn set_health_reports_bad_expressions() {
+    let mut harness = Harness::new(true);
+    harness.keys("h").press(KeyCode::Backspace).keys("d");
+    harness.press(KeyCode::Enter);
+
+    assert_eq!(harness.app.mode, Mode::SetHealth);
+    assert_eq!(harness.creature(0).health, 5);
+    assert!(harness.screen_contains("Can't roll 'd'"));
+}
+
+#[test]
+fn health_shift_damages_and_heals() {
+    let mut harness = Harness::new(true);
+    harness.keys("-3");
+    assert!(harness.screen()[1].contains("5 -3"));
+    harness.press(KeyCode::Enter);
+    assert_eq!(harness.creature(0).health, 2);
+
+    harness.keys("+10").press(KeyCode::Enter);
+    assert_eq!(harness.creature(0).health, 12);
+
+    harness.keys("-4").press(KeyCode::Esc);
+    assert_eq!(harness.creature(0).health, 12);
+}
+
+#[test]
+fn health_shift_can_be_undone() {
+    let mut harness = Harness::new(true);
+    harness.keys("-3").press(KeyCode::Enter);
+    assert_eq!(harness.creature(0).health, 2);
+
+    harness.keys("u");
+    assert_eq!(harness.creature(0).health, 5);
+    harness.keys("U");
+    assert_eq!(harness.creature(0).health, 2);
+}
+
+#[test]
+fn sort_by_name_and_health() {
+    let mut harness = Harness::new(true);
+    harness.keys("s");
+    assert!(harness.screen_contains("Sort by initiative: i/I Health: h/H"));
+
+    harness.keys("n");
+    assert_eq!(harness.app.mode, Mode::Normal);
+    assert!(harness.row_of("Boblin") < harness.row_of("Chodlin"));
+    assert!(harness.row_of("Chodlin") < harness.row_of("Goblin"));
+    // Selection follows the creature
+    assert_eq!(harness.app.selected_creature, Some(2));
+
+    harness.keys("sH");
+    assert_eq!(harness.row_of("Goblin"), 1);
+}
+
+#[test]
+fn help_opens_and_closes() {
+    let mut harness = Harness::new(true);
+    harness.keys("?");
+    assert!(harness.screen_contains("Hotkeys"));
+    assert!(harness.screen_contains("Open this help message: ?"));
+
+    // Only Esc leaves help
+    harness.keys("j");
+    assert_eq!(harness.app.mode, Mode::Help);
+    harness.press(KeyCode::Esc);
+    assert_eq!(harness.app.mode, Mode::Normal);
+}
+
+#[test]
+fn meta_menu_navigation() {
+    let mut harness = Harness::new(true);
+    harness.press(KeyCode::Esc);
+    assert!(harness.screen_contains("Return to normal mode"));
+    assert!(harness.screen_contains("Initiative ties: enemies first"));
+
+    harness.keys("jjjj").press(KeyCode::Enter);
+    assert!(harness.screen_contains("Initiative ties: higher modifier first"));
+
+    harness.keys("jjjjj").press(KeyCode::Enter);
+    assert!(!harness.app.running);
+}
+
+#[test]
+fn meta_menu_returns_to_normal_mode() {
+    let mut harness = Harness::new(true);
+    harness.press(KeyCode::Esc).press(KeyCode::Enter);
+    assert_eq!(harness.app.mode, Mode::Normal);
+
+    harness
+        .press(KeyCode::Esc)
+        .keys("k")
+        .keys("j")
+        .press(KeyCode::Esc);
+    assert_eq!(harness.app.mode, Mode::Normal);
+    assert!(harness.app.running);
+}
+
+#[test]
+fn rebound_keys_drive_navigation_and_instructions() {
+    let mut harness = Harness::new(true);
+    let mut keymap = Keymap::default();
+    keymap.bind(Action::SelectNext, vec![KeyCode::Down]);
+    harness.app = harness.app.clone().with_keymap(keymap);
+
+    harness.keys("j");
+    assert_eq!(harness.app.selected_creature, Some(0));
+    harness.press(KeyCode::Down);
+    assert_eq!(harness.app.selected_creature, Some(1));
+
+    harness.press(KeyCode::Esc);
+    assert!(harness.screen_contains("Navigate: Down/k"));
+    harness.press(KeyCode::Down);
+    assert_eq!(harness.app.mode, Mode::Meta(1));
+}
+
+#[test]
+fn rebound_mode_keys_work_and_show_up_in_the_banners() {
+    let mut harness = Harness::new(true);
+    let mut keymap = Keymap::default();
+    keymap.bind(Action::SortByName, vec![KeyCode::Char('a')]);
+    keymap.bind(Action::IncreaseCondition, vec![KeyCode::Char('=')]);
+    harness.app = harness.app.clone().with_keymap(keymap);
+
+    harness.keys("s");
+    assert!(harness.screen_contains("Name: a/N"));
+    harness.keys("n");
+    assert_eq!(harness.app.mode, Mode::Sort);
+    harness.keys("a");
+    assert_eq!(harness.app.mode, Mode::Normal);
+    assert_eq!(harness.creature(0).name, "Boblin");
+
+    // Normal mode keys stay, even where a mode uses the same key
+    harness.keys("e");
+    assert!(harness.screen_contains("Add/increase: ="));
+    harness.keys("+").press(KeyCode::Enter);
+    assert!(harness.app.encounter.creatures[2].conditions.is_empty());
+    harness.keys("=");
+    assert_eq!(harness.app.encounter.creatures[2].conditions.len(), 1);
+}
+
+#[test]
+fn dark_theme_inverts_the_selected_row() {
+    let mut harness = Harness::new(true);
+    let buffer = harness.buffer();
+    // Inside the border, on the selected and the next row
+    assert_eq!(buffer[(1, 1)].fg, Color::Black);
+    assert_eq!(buffer[(1, 1)].bg, Color::White);
+    assert_eq!(buffer[(1, 2)].fg, Color::White);
+    assert_eq!(buffer[(1, 2)].bg, Color::Black);
+}
+
+#[test]
+fn no_color_theme_has_no_colors() {
+    let mut harness = Harness::new(true);
+    harness.app = harness
+        .app
+        .clone()
+        .with_theme(Theme::named(ThemeName::NoColor));
+    harness.keys("m10").press(KeyCode::Enter);
+    harness.keys("t");
+
+    for screen in [harness.buffer(), harness.keys("?").buffer()] {
+        for cell in &screen.content {
+            assert_eq!(cell.fg, Color::Reset, "'{}' has a color", cell.symbol());
+            assert_eq!(cell.bg, Color::Reset, "'{}' has a color", cell.symbol());
+        }
+    }
+    harness.press(KeyCode::Esc);
+    assert!(harness.buffer()[(1, 1)]
+        .modifier
+        .contains(Modifier::REVERSED));
+}
+
+#[test]
+fn clicking_a_row_selects_it() {
+    let mut harness = Harness::new(true);
+    harness.click(10, 3);
+    assert_eq!(harness.app.selected_creature, Some(2));
+    assert!(harness.screen_contains("The goblin"));
+
+    // Below the last creature and on the border
+    harness.click(10, 4).click(0, 2);
+    assert_eq!(harness.app.selected_creature, Some(2));
+}
+
+#[test]
+fn double_clicking_health_edits_it() {
+    let mut harness = Harness::new(true);
+    let column = harness.screen()[2].find('4').unwrap() as u16;
+    harness.click(column, 2).click(column, 2);
+    assert_eq!(harness.app.selected_creature, Some(1));
+    assert_eq!(harness.app.mode, Mode::SetHealth);
+
+    harness
+        .press(KeyCode::Backspace)
+        .keys("9")
+        .press(KeyCode::Enter);
+    assert_eq!(harness.creature(1).health, 9);
+}
+
+#[test]
+fn double_clicking_the_name_renames() {
+    let mut harness = Harness::new(true);
+    harness.click(6, 1).click(6, 1);
+    assert_eq!(harness.app.mode, Mode::Rename("Goblin".into()));
+}
+
+#[test]
+fn scrolling_moves_the_selection_without_wrapping() {
+    let mut harness = Harness::new(true);
+    for _ in 0..5 {
+        harness.mouse(MouseEventKind::ScrollDown, 10, 2);
+    }
+    assert_eq!(harness.app.selected_creature, Some(2));
+
+    harness.mouse(MouseEventKind::ScrollUp, 10, 2);
+    assert_eq!(harness.app.selected_creature, Some(1));
+
+    // Scrolling over the notes leaves the table alone
+    harness.mouse(MouseEventKind::ScrollUp, 10, 10);
+    assert_eq!(harness.app.selected_creature, Some(1));
+}
+
+#[test]
+fn clicks_are_ignored_while_editing() {
+    let mut harness = Harness::new(true);
+    harness.keys("-");
+    harness.click(10, 3);
+    assert_eq!(harness.app.selected_creature, Some(0));
+    assert_eq!(
+        harness.app.mode,
+        Mode::HealthShift(HealthShift::Decrease(0))
+    );
+}
+
+fn horde(size: usize) -> Harness {
+    let mut harness = Harness::new(false);
+    for number in 1..=size {
+        harness
+            .app
+            .encounter
+            .add(Creature::new(format!("Zombie {}", number)));
+    }
+    harness.keys("K");
+    harness
+}
+
+#[test]
+fn big_tables_scroll_with_the_selection() {
+    let mut harness = horde(40);
+    // Table, status line and a minimal notes pane
+    assert_eq!(harness.row_of(" Notes "), 13);
+    assert!(harness.screen_contains("Zombie 10 "));
+    assert!(!harness.screen_contains("Zombie 11 "));
+
+    harness.keys("J");
+    assert_eq!(harness.app.table_offset, 30);
+    assert_eq!(harness.row_of("Zombie 40"), 10);
+    assert!(!harness.screen_contains("Zombie 30 "));
+
+    // Moving up inside the viewport doesn't scroll
+    harness.keys("kkk");
+    assert_eq!(harness.app.table_offset, 30);
+    harness.keys("K");
+    assert_eq!(harness.app.table_offset, 0);
+}
+
+#[test]
+fn big_tables_get_a_scrollbar() {
+    let right_border = |screen: &[String], rows: usize| -> String {
+        screen[1..=rows]
+            .iter()
+            .map(|line| line.chars().last().unwrap())
+            .collect()
+    };
+
+    let screen = horde(40).screen();
+    let scrollbar = right_border(&screen, 10);
+    assert!(scrollbar.starts_with('▲'));
+    assert!(scrollbar.ends_with('▼'));
+
+    let small = Harness::new(true).screen();
+    assert_eq!(right_border(&small, 3), "║║║");
+}
+
+#[test]
+fn clicks_land_on_the_scrolled_rows() {
+    let mut harness = horde(40);
+    harness.keys("J").click(10, 1);
+    assert_eq!(harness.app.selected_creature, Some(30));
+    assert_eq!(harness.app.table_offset, 30);
+}
+
+#[test]
+fn deleting_at_the_bottom_scrolls_back() {
+    let mut harness = horde(12);
+    harness.keys("J");
+    assert_eq!(harness.app.table_offset, 2);
+    harness.keys("dd");
+    assert_eq!(harness.app.table_offset, 0);
+    assert!(harness.screen_contains("Zombie 1 "));
+}
+
+#[test]
+fn armor_class_and_saves_show_above_the_notes() {
+    let mut harness = Harness::new(true);
+    harness.keys("v");
+    for _ in 0.."0 +0 +0 +0 +0".len() {
+        harness.press(KeyCode::Backspace);
+    }
+    harness.keys("18 9 +7");
+    assert!(harness.screen_contains("AC Fort Ref Will Perception: 18 9 +7"));
+
+    harness.press(KeyCode::Enter);
+    assert_eq!(harness.creature(0).armor_class, 18);
+    assert_eq!(harness.creature(0).saves.reflex, 7);
+    assert_eq!(harness.creature(0).saves.will, 0);
+    assert!(harness.screen_contains("Notes - AC 18, Fort +9, Ref +7, Will +0, Perception +0"));
+}
+
+#[test]
+fn saved_templates_are_added_back_through_the_picker() {
+    let mut harness = Harness::new(true);
+    harness.app.encounter.creatures[0].armor_class = 16;
+    harness.keys("S");
+    assert_eq!(
+        harness.app.status.as_deref(),
+        Some("Saved Goblin to the bestiary")
+    );
+
+    harness.keys("jS");
+    harness.keys("A");
+    assert!(harness.screen_contains("Bestiary"));
+    harness.keys("gbl").press(KeyCode::Enter);
+
+    assert_eq!(harness.app.encounter.creatures.len(), 4);
+    assert_eq!(harness.app.selected_creature, Some(3));
+    let goblin = harness.creature(3);
+    assert_eq!(goblin.name, "Goblin");
+    assert_eq!(goblin.armor_class, 16);
+    assert_eq!((goblin.health, goblin.max_health), (5, 5));
+    assert!(harness.screen_contains("Very gobliny"));
+}
+
+#[test]
+fn bestiary_picker_ranks_and_cancels() {
+    let mut harness = Harness::new(false);
+    for name in ["Gargoyle", "Giant Bat", "Goblin Warrior"] {
+        harness.app.bestiary.insert(Template {
+            name: name.into(),
+            ..Default::default()
+        });
+    }
+
+    // Word starts beat letters in the middle of a word, Gargoyle has no b at all
+    harness.keys("Agb");
+    assert_eq!(harness.app.bestiary.search("gb"), vec![1, 2]);
+    harness.press(KeyCode::Down).press(KeyCode::Enter);
+    assert_eq!(harness.creature(0).name, "Goblin Warrior");
+
+    harness.keys("Ax").press(KeyCode::Enter);
+    assert!(harness.screen_contains("No matches"));
+    harness.press(KeyCode::Esc);
+    assert_eq!(harness.app.mode, Mode::Normal);
+    assert_eq!(harness.app.encounter.creatures.len(), 1);
+}
+
+#[test]
+fn empty_bestiary_says_how_to_fill_it() {
+    let mut harness = Harness::new(true);
+    harness.keys("A");
+    assert_eq!(harness.app.mode, Mode::Normal);
+    assert!(harness.screen_contains("save a creature to it with S"));
+}
+
+#[test]
+fn meta_menu_imports_foundry_npcs() {
+    let path =
+        std::env::temp_dir().join(format!("combat-tracker-import-{}.json", std::process::id()));
+    std::fs::write(
+        &path,
+        r#"{"name": "Goblin Warrior", "type": "npc", "system": {
+            "attributes": {"ac": {"value": 16}, "hp": {"max": 6}},
+            "perception": {"mod": 2},
+            "saves": {"fortitude": {"value": 5}, "reflex": {"value": 7}, "will": {"value": 3}}
+        }}"#,
+    )
+    .unwrap();
+
+    let mut harness = Harness::new(true);
+    harness
+        .press(KeyCode::Esc)
+        .keys("jjj")
+        .press(KeyCode::Enter);
+    assert_eq!(harness.app.mode, Mode::Import(String::new()));
+    harness
+        .keys(&path.display().to_string())
+        .press(KeyCode::Enter);
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(harness.app.mode, Mode::Normal);
+    assert_eq!(harness.app.selected_creature, Some(3));
+    assert_eq!(harness.creature(3).health, 6);
+    assert!(harness.screen_contains("Notes - AC 16, Fort +5, Ref +7, Will +3, Perception +2"));
+}
+
+#[test]
+fn recovery_keeps_imports_and_is_offered_over_loaded_encounters() {
+    let dir = std::env::temp_dir().join(format!("combat-tracker-recover-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let journal = dir.join("autosave.json");
+    let loaded = dir.join("loaded.json");
+    let import = dir.join("import.json");
+    let save = App::new(true).to_save();
+    save.write(&journal).unwrap();
+    save.write(&loaded).unwrap();
+    std::fs::write(
+        &import,
+        r#"{"name": "Kobold", "type": "npc", "system": {"attributes": {"hp": {"max": 6}}}}"#,
+    )
+    .unwrap();
+
+    // Imports go into the crashed session when it is taken back
+    let mut app = App::new(false)
+        .with_autosave(Autosave::at(journal.clone()))
+        .with_imports(std::slice::from_ref(&import))
+        .unwrap();
+    assert_eq!(app.mode, Mode::Recover);
+    app.handle_event(Event::Key(KeyEvent::new(
+        KeyCode::Char('y'),
+        KeyModifiers::NONE,
+    )));
+    let names: Vec<_> = app
+        .encounter
+        .creatures
+        .iter()
+        .map(|c| c.name.as_str())
+        .collect();
+    assert_eq!(names, ["Goblin", "Chodlin", "Boblin", "Kobold"]);
+    assert_eq!(app.selected_creature, Some(3));
+
+    // Nothing to recover when the journal holds what was loaded
+    save.write(&journal).unwrap();
+    let app = App::load(&loaded)
+        .unwrap()
+        .with_autosave(Autosave::at(journal.clone()));
+    assert_eq!(app.mode, Mode::Normal);
+
+    // A journal that got further than the loaded file is offered, and kept until answered
+    let mut crashed = save.clone();
+    crashed.encounter.creatures.truncate(1);
+    crashed.selected_creature = Some(0);
+    crashed.write(&journal).unwrap();
+    let mut harness = Harness::from_app(
+        App::load(&loaded)
+            .unwrap()
+            .with_autosave(Autosave::at(journal.clone())),
+    );
+    assert_eq!(harness.app.mode, Mode::Recover);
+    assert!(harness.screen_contains("autosave with 1 creature is newer than"));
+    harness.keys("x");
+    assert_eq!(
+        SaveFile::load(&journal).unwrap().encounter,
+        crashed.encounter
+    );
+    harness.keys("y");
+    assert_eq!(harness.app.mode, Mode::Normal);
+    assert_eq!(harness.app.encounter, crashed.encounter);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn failed_imports_stay_in_the_prompt() {
+    let mut harness = Harness::new(false);
+    harness
+        .press(KeyCode::Esc)
+        .keys("jjj")
+        .press(KeyCode::Enter);
+    harness.keys("/no/such/file.json").press(KeyCode::Enter);
+
+    assert_eq!(harness.app.mode, Mode::Import("/no/such/file.json".into()));
+    assert!(harness
+        .app
+        .status
+        .as_ref()
+        .unwrap()
+        .starts_with("Can't import"));
+    harness.press(KeyCode::Esc);
+    assert_eq!(harness.app.mode, Mode::Meta(3));
+}
+
+#[test]
+fn copies_are_numbered_after_the_highest() {
+    let mut harness = Harness::new(true);
+    harness.keys("cc");
+    assert_eq!(harness.creature(1).name, "Goblin 2");
+    assert_eq!(harness.creature(2).name, "Goblin 3");
+
+    harness.keys("C3").press(KeyCode::Enter);
+    assert_eq!(
+        harness.app.status.as_deref(),
+        Some("Added Goblin 4 to Goblin 6")
+    );
+    let names: Vec<&str> = harness.app.encounter.creatures[..6]
+        .iter()
+        .map(|creature| creature.name.as_str())
+        .collect();
+    assert_eq!(
+        names,
+        ["Goblin", "Goblin 2", "Goblin 3", "Goblin 4", "Goblin 5", "Goblin 6"]
+    );
+    assert!(harness.screen_contains("Very gobliny"));
+}
+
+#[test]
+fn copies_can_be_lettered() {
+    let mut harness = Harness::new(true);
+    // Copies are named, in the menu
+    harness
+        .press(KeyCode::Esc)
+        .keys("jjjjj")
+        .press(KeyCode::Enter);
+    assert!(harness.screen_contains("Copies are named: Goblin B"));
+    harness.press(KeyCode::Esc).keys("jjc");
+    assert_eq!(harness.creature(3).name, "Boblin B");
+}
+
+#[test]
+fn copies_reroll_health_dice_when_asked() {
+    let mut harness = Harness::new(true);
+    harness.keys("m").press(KeyCode::Backspace).keys("10d8");
+    harness.press(KeyCode::Enter);
+    assert_eq!(harness.creature(0).health_dice.as_deref(), Some("10d8"));
+
+    harness.app.encounter.copies.reroll_health = true;
+    harness.keys("C2").press(KeyCode::Enter);
+    let rolled: Vec<i32> = (0..3)
+        .map(|index| harness.creature(index).max_health)
+        .collect();
+    assert_ne!(rolled[1..], [rolled[0]; 2], "{:?}", rolled);
+    assert_eq!(harness.creature(1).health, rolled[1]);
+}
+
+#[test]
+fn copy_count_is_checked() {
+    let mut harness = Harness::new(true);
+    harness.keys("C0").press(KeyCode::Enter);
+    assert_eq!(harness.app.mode, Mode::Copies);
+    assert!(harness
+        .app
+        .status
+        .as_ref()
+        .unwrap()
+        .starts_with("Can make 1 to"));
+    harness.press(KeyCode::Esc);
+    assert_eq!(harness.app.encounter.creatures.len(), 3);
+
+    // Names have to stay apart, so copies stop when the tags run out
+    harness.app.encounter.creatures[0].name = "Goblin 4294967295".into();
+    harness.keys("c");
+    assert_eq!(
+        harness.app.status.as_deref(),
+        Some("Can't number 1 more copy of Goblin 4294967295")
+    );
+    assert_eq!(harness.app.encounter.creatures.len(), 3);
+}
+
+#[test]
+fn marked_creatures_save_against_area_damage() {
+    let mut harness = Harness::new(true);
+    harness.keys(" j j");
+    assert!(harness.screen_contains("Creatures - 2 marked"));
+    assert!(harness.screen_contains("● Goblin"));
+
+    harness.keys("-4").press(KeyCode::Enter);
+    assert!(harness.screen_contains("4 damage, basic save"));
+    assert!(harness.screen_contains("Leave the DC empty"));
+    harness.press(KeyCode::Enter);
+    // Goblin crits the save, Chodlin keeps the default failure
+    harness.keys("4").press(KeyCode::Enter);
+
+    assert_eq!(harness.app.mode, Mode::Normal);
+    assert_eq!(harness.creature(0).health, 5);
+    assert_eq!(harness.creature(1).health, 0);
+    assert_eq!(harness.creature(2).health, 4);
+    assert_eq!(
+        harness.app.status.as_deref(),
+        Some("Damage taken: Goblin 0, Chodlin 4. Chodlin was defeated")
+    );
+}
+
+#[test]
+fn save_outcomes_halve_and_double() {
+    let mut harness = Harness::new(true);
+    harness.keys(" j j ");
+    harness
+        .keys("-3")
+        .press(KeyCode::Enter)
+        .press(KeyCode::Enter);
+    harness.press(KeyCode::Right).keys("j");
+    harness.press(KeyCode::Left).press(KeyCode::Enter);
+
+    let health: Vec<i32> = (0..3).map(|index| harness.creature(index).health).collect();
+    assert_eq!(health, [4, -2, 1]);
+}
+
+#[test]
+fn basic_saves_are_rolled_against_the_dc() {
+    let mut harness = Harness::new(true);
+    harness.app.encounter.creatures[0].saves.will = 4;
+    harness.keys(" j ");
+    harness.keys("-6").press(KeyCode::Enter);
+    harness.keys("w").press(KeyCode::Tab);
+    assert!(matches!(
+        harness.app.mode,
+        Mode::SaveDc {
+            save: SaveKind::Fortitude,
+            ..
+        }
+    ));
+    harness.keys("w18").press(KeyCode::Enter);
+    assert!(harness.screen_contains("6 damage, DC 18 basic Will"));
+
+    let Mode::SaveOutcomes { outcomes, .. } = harness.app.mode.clone() else {
+        panic!("saves weren't rolled");
+    };
+    assert_eq!(outcomes.len(), 2);
+    let goblin = outcomes[0].check.unwrap();
+    assert_eq!(goblin, Check::new(goblin.natural, 4, 18));
+    assert_eq!(outcomes[0].degree, goblin.degree);
+
+    harness.press(KeyCode::Enter);
+    assert_eq!(
+        harness.creature(0).health,
+        5 - goblin.degree.basic_damage(6) as i32
+    );
+    assert_eq!(
+        harness.creature(1).health,
+        4 - outcomes[1].degree.basic_damage(6) as i32
+    );
+}
+
+#[test]
+fn basic_save_works_without_marks() {
+    let mut harness = Harness::new(true);
+    harness.keys("jB8");
+    assert!(harness.screen_contains("-8"));
+    harness.press(KeyCode::Enter).press(KeyCode::Enter);
+    // Only the hovered creature, and a success halves the damage
+    harness.keys("3").press(KeyCode::Enter);
+    assert_eq!(harness.creature(0).health, 5);
+    assert_eq!(harness.creature(1).health, 0);
+}
+
+#[test]
+fn typed_damage_goes_through_weaknesses_and_resistances() {
+    let mut harness = Harness::new(true);
+    harness
+        .keys("wweak fire 5, resist physical 3")
+        .press(KeyCode::Enter);
+    assert!(harness.screen_contains("weak fire 5; resist physical 3"));
+
+    harness.keys("-2 fire").press(KeyCode::Enter);
+    assert_eq!(harness.creature(0).health, -2);
+    assert_eq!(
+        harness.app.status.as_deref(),
+        Some("Damage taken: Goblin 7 (weak fire 5). Goblin was defeated")
+    );
+    harness.keys("-4 slashing, 1").press(KeyCode::Enter);
+    assert_eq!(harness.creature(0).health, -4);
+
+    harness.keys("-3 frie").press(KeyCode::Enter);
+    assert_eq!(
+        harness.app.mode,
+        Mode::HealthShift(HealthShift::Decrease(0))
+    );
+    assert_eq!(
+        harness.app.status.as_deref(),
+        Some("Can't roll '3 frie': 'frie' is not a damage type")
+    );
+}
+
+#[test]
+fn basic_saves_halve_before_weaknesses() {
+    let mut harness = Harness::new(true);
+    harness.keys("wweak fire 5").press(KeyCode::Enter);
+    harness.keys(" j ");
+    harness
+        .keys("B4 fire")
+        .press(KeyCode::Enter)
+        .press(KeyCode::Enter);
+    assert!(harness.screen_contains("4 fire damage, basic save"));
+    // Both fail until told otherwise, the goblin takes its weakness on top
+    assert!(harness.screen_contains("Failure           -9"));
+
+    harness.keys("3").press(KeyCode::Enter);
+    assert_eq!(harness.creature(0).health, -2);
+    assert_eq!(harness.creature(1).health, 0);
+}
+
+#[test]
+fn persistent_damage_ticks_at_the_end_of_turn() {
+    let mut harness = Harness::new(true);
+    harness
+        .keys("-2, 1d4 persistent fire")
+        .press(KeyCode::Enter);
+    harness.keys("-1d6 persistent fire").press(KeyCode::Enter);
+    harness.keys("-1d4 persistent fire").press(KeyCode::Enter);
+    assert_eq!(harness.creature(0).health, 3);
+    assert!(harness.screen_contains("1d6 persistent fire"));
+    assert!(!harness.screen_contains("1d4"));
+
+    // Nothing happens when the turn starts, only when it ends
+    harness.keys("t");
+    assert_eq!(harness.app.mode, Mode::Normal);
+    harness.keys("t");
+    assert!(harness.screen_contains("End of Goblin's turn"));
+    let Mode::PersistentDamage { ticks, .. } = harness.app.mode.clone() else {
+        panic!("no persistent damage prompt");
+    };
+    let taken = ticks[0].damage.total() as i32;
+
+    // The player rolled their own flat check and passed
+    harness.press(KeyCode::Right).press(KeyCode::Enter);
+    assert_eq!(harness.app.mode, Mode::TurnSummary);
+    assert_eq!(
+        harness.app.turn_summary,
+        [
+            format!("Goblin: took {} persistent fire", taken),
+            "Goblin: was defeated".to_string(),
+            "Goblin: 1d6 persistent fire ended".to_string()
+        ]
+    );
+    assert_eq!(harness.creature(0).health, 3 - taken);
+    assert!(harness.creature(0).persistent.is_empty());
+}
+
+#[test]
+fn persistent_damage_can_continue_or_be_skipped() {
+    let mut harness = Harness::new(true);
+    harness.keys("-1 persistent bleed").press(KeyCode::Enter);
+    harness.keys("tt");
+    harness.press(KeyCode::Left).press(KeyCode::Enter);
+    assert_eq!(harness.creature(0).health, 4);
+    assert_eq!(harness.creature(0).persistent.len(), 1);
+
+    harness.press(KeyCode::Enter).keys("ttt");
+    assert!(matches!(harness.app.mode, Mode::PersistentDamage { .. }));
+    harness.press(KeyCode::Esc);
+    assert_eq!(harness.app.mode, Mode::Normal);
+    assert_eq!(harness.creature(0).health, 4);
+}
+
+#[test]
+fn defeated_creatures_are_greyed_out_or_removed() {
+    let mut harness = Harness::new(true);
+    harness.keys("-5").press(KeyCode::Enter);
+    assert!(harness.creature(0).dead);
+    assert_eq!(harness.app.status.as_deref(), Some("Goblin was defeated"));
+    assert!(harness.screen_contains("Defeated"));
+
+    harness.keys("+1").press(KeyCode::Enter);
+    assert!(!harness.creature(0).dead);
+    assert_eq!(
+        harness.app.status.as_deref(),
+        Some("Goblin is back in the fight")
+    );
+
+    harness.press(KeyCode::Esc).keys("jjjjjjjj");
+    assert!(harness.screen_contains("Remove defeated creatures outside the party: no"));
+    harness.press(KeyCode::Enter).press(KeyCode::Esc);
+    harness.keys("j-4").press(KeyCode::Enter);
+    assert_eq!(harness.app.encounter.creatures.len(), 2);
+    assert_eq!(harness.creature(1).name, "Boblin");
+    assert_eq!(harness.app.selected_creature, Some(1));
+
+    // Also when nothing is selected
+    harness.press(KeyCode::Esc).keys("jjjjjjjj");
+    harness.press(KeyCode::Enter).press(KeyCode::Esc);
+    harness.keys("-4").press(KeyCode::Enter);
+    assert_eq!(harness.app.encounter.creatures.len(), 2);
+    harness.app.selected_creature = None;
+    harness.press(KeyCode::Esc).keys("jjjjjjjj");
+    harness.press(KeyCode::Enter).press(KeyCode::Esc);
+    assert_eq!(harness.app.encounter.creatures.len(), 1);
+    assert_eq!(harness.creature(0).name, "Goblin");
+    assert_eq!(harness.app.selected_creature, None);
+}
+
+#[test]
+fn characters_are_dying_instead_of_defeated() {
+    let mut harness = Harness::new(true);
+    harness.keys("f-7").press(KeyCode::Enter);
+    assert_eq!(harness.creature(0).health, 0);
+    assert_eq!(harness.app.status.as_deref(), Some("Goblin is dying 1"));
+    assert!(harness.screen_contains("Dying 1, Unconscious"));
+
+    harness.keys("+3").press(KeyCode::Enter);
+    assert_eq!(
+        harness.app.status.as_deref(),
+        Some("Goblin is no longer dying, wounded 1")
+    );
+    harness.keys("-9").press(KeyCode::Enter);
+    assert_eq!(harness.app.status.as_deref(), Some("Goblin is dying 2"));
+
+    // The recovery check comes up when their turn starts, after the enemies on a tie
+    harness.keys("ttt");
+    assert!(harness.screen_contains("Goblin's recovery check"));
+    assert!(harness.screen_contains("DC 12 flat check"));
+    harness.keys("2").press(KeyCode::Enter);
+    assert_eq!(harness.app.status.as_deref(), Some("Goblin is dying 3"));
+
+    harness.keys("-1").press(KeyCode::Enter);
+    assert_eq!(harness.app.status.as_deref(), Some("Goblin died"));
+    assert!(harness.creature(0).dead);
+    assert!(harness.screen_contains("Dead"));
+}
+
+#[test]
+fn editing_max_health_only_fills_fresh_creatures() {
+    let mut harness = Harness::new(false);
+    harness.keys("aKyra").press(KeyCode::Enter);
+    harness.keys("m").press(KeyCode::Backspace).keys("10");
+    harness.press(KeyCode::Enter);
+    assert_eq!(harness.creature(0).health, 10);
+
+    // A character who is down stays down
+    harness.keys("f-10").press(KeyCode::Enter);
+    assert_eq!(harness.app.status.as_deref(), Some("Kyra is dying 1"));
+    harness
+        .keys("m")
+        .press(KeyCode::Backspace)
+        .press(KeyCode::Backspace);
+    harness.keys("12").press(KeyCode::Enter);
+    let kyra = harness.creature(0);
+    assert_eq!((kyra.health, kyra.max_health), (0, 12));
+    assert!(harness.screen_contains("Dying 1, Unconscious"));
+}
+
+#[test]
+fn sides_are_filtered_hidden_from_players_and_break_ties() {
+    let mut harness = Harness::new(true);
+    harness.keys("fjff");
+    assert!(harness.screen_contains("Goblin (PC)"));
+    assert!(harness.screen_contains("Chodlin (ally)"));
+
+    // Cycles through the sides there are, enemies first
+    harness.keys("F");
+    assert!(harness.screen_contains("Creatures - enemies only"));
+    assert!(!harness.screen_contains("Goblin"));
+    assert_eq!(harness.app.selected_creature, Some(2));
+    harness.keys("Fj");
+    assert!(harness.screen_contains("party only"));
+    assert_eq!(harness.app.selected_creature, Some(0));
+    harness.keys("FF");
+    assert!(harness.screen_contains("Goblin (PC)"));
+    assert!(harness.screen_contains("Boblin"));
+
+    // Enemy health turns into words, the party's stays in numbers
+    harness.app.encounter.creatures[0].max_health = 5;
+    harness.app.encounter.creatures[2].max_health = 4;
+    harness.keys("P");
+    assert!(harness.screen_contains("player view"));
+    assert!(harness.screen_contains("5/5"));
+    assert!(!harness.screen_contains("4/4"));
+    assert_eq!(harness.row_of("Boblin"), harness.row_of("unhurt"));
+    harness.keys("J-2").press(KeyCode::Enter);
+    assert_eq!(harness.row_of("Boblin"), harness.row_of("bloodied"));
+
+    // Foes act first on a tie, then allies, then the party
+    harness.keys("t");
+    assert_eq!(harness.app.encounter.active_creature, Some(2));
+    harness.keys("t");
+    assert_eq!(harness.app.encounter.active_creature, Some(1));
+    harness.keys("t");
+    assert_eq!(harness.app.encounter.active_creature, Some(0));
+}
+
+#[test]
+fn player_view_hides_enemy_details() {
+    let mut harness = Harness::new(true);
+    let goblin = &mut harness.app.encounter.creatures[0];
+    goblin.armor_class = 16;
+    goblin.adjustments = "weak fire 5".parse().unwrap();
+    assert!(harness.screen_contains("Notes - AC 16"));
+    assert!(harness.screen_contains("weak fire 5"));
+    assert!(harness.screen_contains("Very gobliny"));
+
+    harness.keys("P");
+    assert!(harness.screen_contains(" Notes "));
+    assert!(!harness.screen_contains("AC 16"));
+    assert!(!harness.screen_contains("weak fire"));
+    assert!(!harness.screen_contains("Very gobliny"));
+
+    // Taking damage shows who got hit, not how hard
+    harness.keys("-2 fire");
+    assert!(!harness.screen()[1].contains('5'));
+    harness.press(KeyCode::Enter);
+    assert_eq!(harness.creature(0).health, -2);
+    assert_eq!(harness.app.status.as_deref(), Some("Goblin was defeated"));
+    harness
+        .keys(" j -1")
+        .press(KeyCode::Enter)
+        .press(KeyCode::Enter);
+    assert!(harness.screen_contains("Chodlin                      Failure"));
+    assert!(!harness.screen_contains("-1"));
+    harness.press(KeyCode::Enter);
+    assert_eq!(
+        harness.app.status.as_deref(),
+        Some("Damage taken: Goblin, Chodlin")
+    );
+
+    // The party's own notes stay up
+    harness.keys("xkf");
+    assert!(harness.screen_contains("player view"));
+    assert!(harness.screen_contains("Notes - AC 16"));
+    assert!(harness.screen_contains("Very gobliny"));
+}
+
+#[test]
+fn conditions_initiative_and_delete_apply_to_all_marked() {
+    let mut harness = Harness::new(true);
+    harness.keys(" jj ");
+    harness.keys("e").press(KeyCode::Enter).press(KeyCode::Esc);
+    assert!(harness.creature(0).conditions.len() == 1);
+    assert!(harness.creature(1).conditions.is_empty());
+    assert!(harness.creature(2).conditions.len() == 1);
+
+    harness.keys("i12").press(KeyCode::Enter);
+    assert_eq!(harness.creature(0).initiative, Some(12));
+    assert_eq!(harness.creature(2).initiative, Some(12));
+
+    harness.keys("d");
+    assert_eq!(harness.app.encounter.creatures.len(), 1);
+    assert_eq!(harness.creature(0).name, "Chodlin");
+    assert_eq!(harness.app.selected_creature, Some(0));
+}
+
+#[test]
+fn marks_clear_and_ctrl_click() {
+    let mut harness = Harness::new(true);
+    harness.app.handle_event(Event::Mouse(MouseEvent {
+        kind: MouseEventKind::Down(MouseButton::Left),
+        column: 10,
+        row: 2,
+        modifiers: KeyModifiers::CONTROL,
+    }));
+    assert!(harness.app.marked.contains(&1));
+
+    harness.keys("x");
+    assert_eq!(harness.app.marked_count(), 0);
+    // Without marks damage goes straight to the hovered creature
+    harness.keys("-1").press(KeyCode::Enter);
+    assert_eq!(harness.creature(1).health, 3);
+}
+
+#[test]
+fn marks_are_not_undone_and_follow_their_creatures() {
+    let mut harness = Harness::new(true);
+    harness.keys(" i12").press(KeyCode::Enter);
+    assert_eq!(harness.creature(0).initiative, Some(12));
+
+    // Undo skips over clearing the marks and goes straight to the edit
+    harness.keys("xu");
+    assert_eq!(harness.creature(0).initiative, None);
+    assert_eq!(harness.app.marked_count(), 0);
+
+    harness.keys(" sn");
+    assert_eq!(harness.creature(2).name, "Goblin");
+    assert_eq!(harness.app.marked, BTreeSet::from([2]));
+    assert!(harness.screen_contains("● Goblin"));
+
+    harness.keys("<");
+    assert_eq!(harness.app.marked, BTreeSet::from([1]));
+    harness.keys("KKc");
+    assert_eq!(harness.creature(1).name, "Boblin 2");
+    assert_eq!(harness.app.marked, BTreeSet::from([2]));
+}
+
+#[test]
+fn undoing_a_sort_drops_the_marks_and_keeps_the_selection() {
+    let mut harness = Harness::new(true);
+    harness.keys(" sn");
+    assert_eq!(harness.app.marked, BTreeSet::from([2]));
+
+    // Boblin is at index 2 again, deleting it would be a surprise
+    harness.keys("u");
+    assert_eq!(harness.creature(2).name, "Boblin");
+    assert_eq!(harness.app.marked_count(), 0);
+    assert_eq!(harness.app.selected_creature, Some(0));
+
+    harness.keys("d");
+    let names: Vec<_> = harness
+        .app
+        .encounter
+        .creatures
+        .iter()
+        .map(|c| c.name.as_str())
+        .collect();
+    assert_eq!(names, ["Chodlin", "Boblin"]);
+
+    // Same for undoing and redoing a swap
+    harness.keys("u >");
+    assert_eq!(harness.app.marked, BTreeSet::from([2]));
+    harness.keys("u");
+    assert_eq!(harness.app.marked_count(), 0);
+    assert_eq!(harness.app.selected_creature, Some(1));
+    harness.keys("U");
+    assert_eq!(harness.app.selected_creature, Some(2));
+    assert_eq!(harness.creature(2).name, "Chodlin");
+}
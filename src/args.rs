@@ -1,9 +1,42 @@
+use std::path::PathBuf;
+
 use clap::Parser;
 
+use combat_tracker::history;
+
+use crate::theme::ThemeName;
+
 #[derive(Debug, Parser)]
 pub struct Args {
     #[arg(long)]
     pub logging: bool,
     #[arg(long)]
     pub init_test_creatures: bool,
+    /// Encounter file to continue from, saving writes back to it
+    #[arg(long)]
+    pub load: Option<PathBuf>,
+    /// Don't journal the session for crash recovery
+    #[arg(long)]
+    pub no_autosave: bool,
+    /// How many steps can be undone
+    #[arg(long, default_value_t = history::DEFAULT_DEPTH)]
+    pub history_depth: usize,
+    /// Seed for dice rolls, for reproducing a session
+    #[arg(long)]
+    pub seed: Option<u64>,
+    /// Config file to use instead of the one in the user config directory
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+    /// Leave the mouse to the terminal, for selecting text
+    #[arg(long)]
+    pub no_mouse: bool,
+    /// Color theme, overrides the config file and NO_COLOR
+    #[arg(long)]
+    pub theme: Option<ThemeName>,
+    /// Bestiary file to use instead of the one in the user data directory
+    #[arg(long)]
+    pub bestiary: Option<PathBuf>,
+    /// Pathbuilder 2e or Foundry pf2e NPC JSON export to add to the encounter, can be repeated
+    #[arg(long, value_name = "PATH")]
+    pub import: Vec<PathBuf>,
 }
@@ -0,0 +1,82 @@
+use std::{
+    fs,
+    io::{self},
+    path::PathBuf,
+};
+
+use log::info;
+
+use combat_tracker::save::SaveFile;
+
+// The journal only exists while a session is running, a clean exit removes it.
+// Finding one on startup means the previous session crashed or was killed.
+#[derive(Debug, Clone)]
+pub struct Autosave {
+    path: PathBuf,
+    // Serialized form of what was last written, used to skip redundant writes
+    last_written: Option<String>,
+}
+
+impl Autosave {
+    pub fn in_state_dir() -> Option<Self> {
+        let dir = dirs::state_dir()
+            .or_else(dirs::data_local_dir)?
+            .join("combat-tracker");
+        Some(Autosave::at(dir.join("autosave.json")))
+    }
+
+    pub fn at(path: PathBuf) -> Self {
+        Autosave {
+            path,
+            last_written: None,
+        }
+    }
+
+    /// Save left behind by a session that didn't exit cleanly
+    pub fn unclean_session(&self) -> Option<SaveFile> {
+        if !self.path.exists() {
+            return None;
+        }
+
+        match SaveFile::load(&self.path) {
+            Ok(save) => Some(save),
+            Err(err) => {
+                info!(
+                    "Ignoring unreadable autosave {}: {}",
+                    self.path.display(),
+                    err
+                );
+                None
+            }
+        }
+    }
+
+    /// Writes the state if it changed since the last call
+    pub fn update(&mut self, save: &SaveFile) -> io::Result<()> {
+        let serialized = serde_json::to_string_pretty(save)?;
+        if self.last_written.as_ref() == Some(&serialized) {
+            return Ok(());
+        }
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        // Write and rename so a crash mid-write can't leave a truncated journal
+        let temp_path = self.path.with_extension("json.tmp");
+        fs::write(&temp_path, &serialized)?;
+        fs::rename(&temp_path, &self.path)?;
+
+        self.last_written = Some(serialized);
+        Ok(())
+    }
+
+    /// Marks the session as cleanly closed
+    pub fn clear(&mut self) -> io::Result<()> {
+        self.last_written = None;
+        match fs::remove_file(&self.path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
+    }
+}
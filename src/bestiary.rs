@@ -0,0 +1,227 @@
+use std::{
+    cmp::Reverse,
+    fs::{self, File},
+    io::{self, BufReader},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    damage::Adjustments,
+    encounter::{Creature, Saves},
+};
+
+/// The parts of a creature worth keeping between fights
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Template {
+    pub name: String,
+    pub max_health: i32,
+    pub armor_class: i32,
+    pub saves: Saves,
+    pub perception: i32,
+    pub adjustments: Adjustments,
+    pub initiative_modifier: i32,
+    pub notes: String,
+}
+
+impl Template {
+    pub fn from_creature(creature: &Creature) -> Self {
+        Template {
+            name: creature.name.clone(),
+            // Creatures without a known maximum are saved at what they have now
+            max_health: if creature.max_health > 0 {
+                creature.max_health
+            } else {
+                creature.health
+            },
+            armor_class: creature.armor_class,
+            saves: creature.saves,
+            perception: creature.perception,
+            adjustments: creature.adjustments.clone(),
+            initiative_modifier: creature.initiative_modifier,
+            notes: creature.notes.clone(),
+        }
+    }
+
+    /// A fresh creature at full health
+    pub fn instantiate(&self) -> Creature {
+        Creature {
+            name: self.name.clone(),
+            health: self.max_health,
+            max_health: self.max_health,
+            armor_class: self.armor_class,
+            saves: self.saves,
+            perception: self.perception,
+            adjustments: self.adjustments.clone(),
+            initiative_modifier: self.initiative_modifier,
+            notes: self.notes.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Bestiary {
+    pub templates: Vec<Template>,
+}
+
+impl Bestiary {
+    pub fn default_path() -> Option<PathBuf> {
+        Some(
+            dirs::data_dir()?
+                .join("combat-tracker")
+                .join("bestiary.json"),
+        )
+    }
+
+    /// A missing file is an empty bestiary
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Bestiary::default()),
+            Err(err) => return Err(err),
+        };
+        serde_json::from_reader(BufReader::new(file)).map_err(|err| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), err),
+            )
+        })
+    }
+
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        // Write and rename so a crash mid-write can't eat the whole bestiary
+        let temp_path = path.with_extension("json.tmp");
+        fs::write(&temp_path, serde_json::to_string_pretty(self)?)?;
+        fs::rename(&temp_path, path)
+    }
+
+    /// Adds the template, replacing any with the same name. Returns whether one was replaced.
+    pub fn insert(&mut self, template: Template) -> bool {
+        match self
+            .templates
+            .iter_mut()
+            .find(|existing| existing.name.eq_ignore_ascii_case(&template.name))
+        {
+            Some(existing) => {
+                *existing = template;
+                true
+            }
+            None => {
+                self.templates.push(template);
+                self.templates
+                    .sort_by_key(|template| template.name.to_lowercase());
+                false
+            }
+        }
+    }
+
+    /// Indices of the templates matching the query, best match first
+    pub fn search(&self, query: &str) -> Vec<usize> {
+        let mut matches: Vec<(usize, u32)> = self
+            .templates
+            .iter()
+            .enumerate()
+            .filter_map(|(index, template)| {
+                fuzzy_score(query, &template.name).map(|score| (index, score))
+            })
+            .collect();
+        // Stable, so equally good matches stay alphabetical
+        matches.sort_by_key(|(_, score)| Reverse(*score));
+        matches.into_iter().map(|(index, _)| index).collect()
+    }
+}
+
+/// Scores the query as a subsequence of the text, ignoring case and spaces in the query.
+/// None if the text doesn't contain it at all. Runs of letters and word starts score higher.
+fn fuzzy_score(query: &str, text: &str) -> Option<u32> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous_match = None;
+    for wanted in query
+        .to_lowercase()
+        .chars()
+        .filter(|ch| !ch.is_whitespace())
+    {
+        let offset = text[position..].iter().position(|ch| *ch == wanted)?;
+        let index = position + offset;
+
+        score += 1;
+        if previous_match.is_some_and(|previous| previous + 1 == index) {
+            score += 4;
+        }
+        if index == 0 || !text[index - 1].is_alphanumeric() {
+            score += 3;
+        }
+        previous_match = Some(index);
+        position = index + 1;
+    }
+    Some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bestiary(names: &[&str]) -> Bestiary {
+        let mut bestiary = Bestiary::default();
+        for name in names {
+            bestiary.insert(Template {
+                name: name.to_string(),
+                ..Default::default()
+            });
+        }
+        bestiary
+    }
+
+    fn found(bestiary: &Bestiary, query: &str) -> Vec<String> {
+        bestiary
+            .search(query)
+            .into_iter()
+            .map(|index| bestiary.templates[index].name.clone())
+            .collect()
+    }
+
+    #[test]
+    fn templates_are_replaced_by_name_ignoring_case() {
+        let mut bestiary = bestiary(&["Goblin Warrior", "Gargoyle"]);
+        assert_eq!(found(&bestiary, ""), ["Gargoyle", "Goblin Warrior"]);
+
+        let replaced = bestiary.insert(Template {
+            name: "goblin warrior".into(),
+            max_health: 6,
+            ..Default::default()
+        });
+        assert!(replaced);
+        assert_eq!(bestiary.templates.len(), 2);
+        assert_eq!(bestiary.templates[1].name, "goblin warrior");
+        assert_eq!(bestiary.templates[1].max_health, 6);
+    }
+
+    #[test]
+    fn word_starts_and_runs_rank_higher() {
+        let bestiary = bestiary(&["Gargoyle", "Giant Bat", "Goblin Warrior"]);
+        assert_eq!(found(&bestiary, "gb"), ["Giant Bat", "Goblin Warrior"]);
+        assert_eq!(found(&bestiary, "GOB"), ["Goblin Warrior"]);
+        // Spaces in the query don't have to line up with the name
+        assert_eq!(found(&bestiary, "gi ant"), ["Giant Bat"]);
+
+        assert!(fuzzy_score("gar", "Gargoyle") > fuzzy_score("gar", "Goblin Warrior"));
+    }
+
+    #[test]
+    fn queries_that_dont_match_find_nothing() {
+        let bestiary = bestiary(&["Gargoyle", "Giant Bat"]);
+        assert!(found(&bestiary, "orc").is_empty());
+        // Letters have to come in order
+        assert!(found(&bestiary, "tag").is_empty());
+        assert_eq!(fuzzy_score("ab", "a"), None);
+    }
+}
@@ -0,0 +1,166 @@
+use std::fmt::Display;
+
+use rand::Rng;
+
+/// How well a check went, from worst to best
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Degree {
+    CriticalFailure,
+    Failure,
+    Success,
+    CriticalSuccess,
+}
+
+impl Degree {
+    pub const ALL: [Degree; 4] = [
+        Degree::CriticalFailure,
+        Degree::Failure,
+        Degree::Success,
+        Degree::CriticalSuccess,
+    ];
+
+    /// Ten over the DC is a critical success, ten under a critical failure
+    pub fn of_check(total: i32, dc: i32) -> Self {
+        // Saturating, a DC is typed in and can be anything
+        if total >= dc.saturating_add(10) {
+            Degree::CriticalSuccess
+        } else if total >= dc {
+            Degree::Success
+        } else if total <= dc.saturating_sub(10) {
+            Degree::CriticalFailure
+        } else {
+            Degree::Failure
+        }
+    }
+
+    pub fn better(&self) -> Self {
+        match self {
+            Degree::CriticalFailure => Degree::Failure,
+            Degree::Failure => Degree::Success,
+            Degree::Success | Degree::CriticalSuccess => Degree::CriticalSuccess,
+        }
+    }
+
+    pub fn worse(&self) -> Self {
+        match self {
+            Degree::CriticalSuccess => Degree::Success,
+            Degree::Success => Degree::Failure,
+            Degree::Failure | Degree::CriticalFailure => Degree::CriticalFailure,
+        }
+    }
+
+    /// Damage taken on a basic saving throw: none, half, all or double
+    pub fn basic_damage(&self, damage: u32) -> u32 {
+        match self {
+            Degree::CriticalSuccess => 0,
+            Degree::Success => damage / 2,
+            Degree::Failure => damage,
+            Degree::CriticalFailure => damage.saturating_mul(2),
+        }
+    }
+}
+
+impl Display for Degree {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Degree::CriticalFailure => "Critical failure",
+            Degree::Failure => "Failure",
+            Degree::Success => "Success",
+            Degree::CriticalSuccess => "Critical success",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SaveKind {
+    Fortitude,
+    #[default]
+    Reflex,
+    Will,
+}
+
+impl SaveKind {
+    pub const ALL: [SaveKind; 3] = [SaveKind::Fortitude, SaveKind::Reflex, SaveKind::Will];
+
+    pub fn next(&self) -> Self {
+        match self {
+            SaveKind::Fortitude => SaveKind::Reflex,
+            SaveKind::Reflex => SaveKind::Will,
+            SaveKind::Will => SaveKind::Fortitude,
+        }
+    }
+}
+
+impl Display for SaveKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            SaveKind::Fortitude => "Fortitude",
+            SaveKind::Reflex => "Reflex",
+            SaveKind::Will => "Will",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A d20 roll against a DC
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Check {
+    pub natural: i32,
+    pub total: i32,
+    pub degree: Degree,
+}
+
+impl Check {
+    pub fn roll(modifier: i32, dc: i32, rng: &mut impl Rng) -> Self {
+        Check::new(rng.random_range(1..=20), modifier, dc)
+    }
+
+    pub fn new(natural: i32, modifier: i32, dc: i32) -> Self {
+        let total = natural.saturating_add(modifier);
+        // A natural 20 or 1 shifts the result one step
+        let degree = match natural {
+            20 => Degree::of_check(total, dc).better(),
+            1 => Degree::of_check(total, dc).worse(),
+            _ => Degree::of_check(total, dc),
+        };
+        Check {
+            natural,
+            total,
+            degree,
+        }
+    }
+}
+
+impl Display for Check {
+    /// Like `18` or `24 (nat 20)`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.natural {
+            1 | 20 => write!(f, "{} (nat {})", self.total, self.natural),
+            _ => write!(f, "{}", self.total),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn natural_rolls_shift_the_degree() {
+        assert_eq!(Check::new(10, 5, 15).degree, Degree::Success);
+        assert_eq!(Check::new(15, 10, 15).degree, Degree::CriticalSuccess);
+        assert_eq!(Check::new(20, 0, 30).degree, Degree::Failure);
+        assert_eq!(Check::new(20, 0, 21).degree, Degree::Success);
+        assert_eq!(Check::new(1, 30, 15).degree, Degree::Success);
+        assert_eq!(Check::new(1, 0, 15).degree, Degree::CriticalFailure);
+    }
+
+    #[test]
+    fn huge_numbers_dont_overflow() {
+        assert_eq!(Check::new(20, 5, i32::MAX).degree, Degree::Failure);
+        assert_eq!(Check::new(10, 5, i32::MIN).degree, Degree::CriticalSuccess);
+        assert_eq!(Check::new(10, i32::MAX, 15).total, i32::MAX);
+        assert_eq!(Degree::CriticalFailure.basic_damage(u32::MAX), u32::MAX);
+    }
+}
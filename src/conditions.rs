@@ -0,0 +1,317 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+// Pathfinder 2e (remaster) conditions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConditionKind {
+    Blinded,
+    Broken,
+    Clumsy,
+    Concealed,
+    Confused,
+    Controlled,
+    Dazzled,
+    Deafened,
+    Doomed,
+    Drained,
+    Dying,
+    Encumbered,
+    Enfeebled,
+    Fascinated,
+    Fatigued,
+    Fleeing,
+    Friendly,
+    Frightened,
+    Grabbed,
+    Helpful,
+    Hidden,
+    Hostile,
+    Immobilized,
+    Indifferent,
+    Invisible,
+    Observed,
+    OffGuard,
+    Paralyzed,
+    Petrified,
+    Prone,
+    Quickened,
+    Restrained,
+    Sickened,
+    Slowed,
+    Stunned,
+    Stupefied,
+    Unconscious,
+    Undetected,
+    Unfriendly,
+    Unnoticed,
+    Wounded,
+}
+
+impl ConditionKind {
+    pub const ALL: [ConditionKind; 41] = [
+        ConditionKind::Blinded,
+        ConditionKind::Broken,
+        ConditionKind::Clumsy,
+        ConditionKind::Concealed,
+        ConditionKind::Confused,
+        ConditionKind::Controlled,
+        ConditionKind::Dazzled,
+        ConditionKind::Deafened,
+        ConditionKind::Doomed,
+        ConditionKind::Drained,
+        ConditionKind::Dying,
+        ConditionKind::Encumbered,
+        ConditionKind::Enfeebled,
+        ConditionKind::Fascinated,
+        ConditionKind::Fatigued,
+        ConditionKind::Fleeing,
+        ConditionKind::Friendly,
+        ConditionKind::Frightened,
+        ConditionKind::Grabbed,
+        ConditionKind::Helpful,
+        ConditionKind::Hidden,
+        ConditionKind::Hostile,
+        ConditionKind::Immobilized,
+        ConditionKind::Indifferent,
+        ConditionKind::Invisible,
+        ConditionKind::Observed,
+        ConditionKind::OffGuard,
+        ConditionKind::Paralyzed,
+        ConditionKind::Petrified,
+        ConditionKind::Prone,
+        ConditionKind::Quickened,
+        ConditionKind::Restrained,
+        ConditionKind::Sickened,
+        ConditionKind::Slowed,
+        ConditionKind::Stunned,
+        ConditionKind::Stupefied,
+        ConditionKind::Unconscious,
+        ConditionKind::Undetected,
+        ConditionKind::Unfriendly,
+        ConditionKind::Unnoticed,
+        ConditionKind::Wounded,
+    ];
+
+    /// Whether the condition carries a number, like Frightened 2
+    pub fn is_valued(&self) -> bool {
+        matches!(
+            self,
+            ConditionKind::Clumsy
+                | ConditionKind::Doomed
+                | ConditionKind::Drained
+                | ConditionKind::Dying
+                | ConditionKind::Enfeebled
+                | ConditionKind::Frightened
+                | ConditionKind::Sickened
+                | ConditionKind::Slowed
+                | ConditionKind::Stunned
+                | ConditionKind::Stupefied
+                | ConditionKind::Wounded
+        )
+    }
+
+    pub fn default_rule(&self) -> TickRule {
+        match self {
+            ConditionKind::Frightened => TickRule::Decrement,
+            _ => TickRule::Persists,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ConditionKind::Blinded => "Blinded",
+            ConditionKind::Broken => "Broken",
+            ConditionKind::Clumsy => "Clumsy",
+            ConditionKind::Concealed => "Concealed",
+            ConditionKind::Confused => "Confused",
+            ConditionKind::Controlled => "Controlled",
+            ConditionKind::Dazzled => "Dazzled",
+            ConditionKind::Deafened => "Deafened",
+            ConditionKind::Doomed => "Doomed",
+            ConditionKind::Drained => "Drained",
+            ConditionKind::Dying => "Dying",
+            ConditionKind::Encumbered => "Encumbered",
+            ConditionKind::Enfeebled => "Enfeebled",
+            ConditionKind::Fascinated => "Fascinated",
+            ConditionKind::Fatigued => "Fatigued",
+            ConditionKind::Fleeing => "Fleeing",
+            ConditionKind::Friendly => "Friendly",
+            ConditionKind::Frightened => "Frightened",
+            ConditionKind::Grabbed => "Grabbed",
+            ConditionKind::Helpful => "Helpful",
+            ConditionKind::Hidden => "Hidden",
+            ConditionKind::Hostile => "Hostile",
+            ConditionKind::Immobilized => "Immobilized",
+            ConditionKind::Indifferent => "Indifferent",
+            ConditionKind::Invisible => "Invisible",
+            ConditionKind::Observed => "Observed",
+            ConditionKind::OffGuard => "Off-Guard",
+            ConditionKind::Paralyzed => "Paralyzed",
+            ConditionKind::Petrified => "Petrified",
+            ConditionKind::Prone => "Prone",
+            ConditionKind::Quickened => "Quickened",
+            ConditionKind::Restrained => "Restrained",
+            ConditionKind::Sickened => "Sickened",
+            ConditionKind::Slowed => "Slowed",
+            ConditionKind::Stunned => "Stunned",
+            ConditionKind::Stupefied => "Stupefied",
+            ConditionKind::Unconscious => "Unconscious",
+            ConditionKind::Undetected => "Undetected",
+            ConditionKind::Unfriendly => "Unfriendly",
+            ConditionKind::Unnoticed => "Unnoticed",
+            ConditionKind::Wounded => "Wounded",
+        }
+    }
+}
+
+/// What happens to a condition when the affected creature's turn ends
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TickRule {
+    #[default]
+    Persists,
+    Decrement,
+    Remove,
+}
+
+impl TickRule {
+    pub fn next(&self) -> Self {
+        match self {
+            TickRule::Persists => TickRule::Decrement,
+            TickRule::Decrement => TickRule::Remove,
+            TickRule::Remove => TickRule::Persists,
+        }
+    }
+
+    pub fn describe(&self) -> &'static str {
+        match self {
+            TickRule::Persists => "persists",
+            TickRule::Decrement => "decrements at end of turn",
+            TickRule::Remove => "ends at end of turn",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Condition {
+    pub kind: ConditionKind,
+    // Only used by valued conditions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<u8>,
+    #[serde(default)]
+    pub rule: TickRule,
+}
+
+impl Condition {
+    pub fn new(kind: ConditionKind) -> Self {
+        Condition {
+            kind,
+            value: kind.is_valued().then_some(1),
+            rule: kind.default_rule(),
+        }
+    }
+}
+
+impl Display for Condition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.value {
+            Some(value) => write!(f, "{} {}", self.kind.name(), value),
+            None => write!(f, "{}", self.kind.name()),
+        }
+    }
+}
+
+/// Adds the condition or bumps its value if it's already there
+pub fn increase(conditions: &mut Vec<Condition>, kind: ConditionKind) {
+    match conditions
+        .iter_mut()
+        .find(|condition| condition.kind == kind)
+    {
+        Some(condition) => {
+            if let Some(value) = condition.value.as_mut() {
+                *value = value.saturating_add(1);
+            }
+        }
+        None => conditions.push(Condition::new(kind)),
+    }
+}
+
+/// Value of the condition, 0 when the creature doesn't have it
+pub fn value(conditions: &[Condition], kind: ConditionKind) -> u8 {
+    conditions
+        .iter()
+        .find(|condition| condition.kind == kind)
+        .map(|condition| condition.value.unwrap_or(1))
+        .unwrap_or(0)
+}
+
+/// Sets the value of the condition, adding it if needed. 0 removes it.
+pub fn set(conditions: &mut Vec<Condition>, kind: ConditionKind, value: u8) {
+    if value == 0 {
+        conditions.retain(|condition| condition.kind != kind);
+        return;
+    }
+    match conditions
+        .iter_mut()
+        .find(|condition| condition.kind == kind)
+    {
+        Some(condition) => condition.value = kind.is_valued().then_some(value),
+        None => conditions.push(Condition {
+            value: kind.is_valued().then_some(value),
+            ..Condition::new(kind)
+        }),
+    }
+}
+
+/// Lowers the value of the condition, removing it once there is nothing left
+pub fn decrease(conditions: &mut Vec<Condition>, kind: ConditionKind) {
+    let Some(index) = conditions
+        .iter()
+        .position(|condition| condition.kind == kind)
+    else {
+        return;
+    };
+
+    match conditions[index].value.as_mut() {
+        Some(value) if *value > 1 => *value -= 1,
+        _ => {
+            conditions.remove(index);
+        }
+    }
+}
+
+/// Switches the condition to the next tick rule, if the creature has it
+pub fn cycle_rule(conditions: &mut [Condition], kind: ConditionKind) {
+    if let Some(condition) = conditions
+        .iter_mut()
+        .find(|condition| condition.kind == kind)
+    {
+        condition.rule = condition.rule.next();
+    }
+}
+
+/// Applies the tick rules, returns a description of everything that changed
+pub fn end_of_turn(conditions: &mut Vec<Condition>) -> Vec<String> {
+    let mut changes = vec![];
+
+    conditions.retain_mut(|condition| match condition.rule {
+        TickRule::Persists => true,
+        TickRule::Remove => {
+            changes.push(format!("{} ended", condition));
+            false
+        }
+        TickRule::Decrement => match condition.value.as_mut() {
+            Some(value) if *value > 1 => {
+                *value -= 1;
+                changes.push(format!("{} reduced to {}", condition.kind.name(), value));
+                true
+            }
+            _ => {
+                changes.push(format!("{} ended", condition.kind.name()));
+                false
+            }
+        },
+    });
+
+    changes
+}
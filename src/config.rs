@@ -0,0 +1,111 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self},
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::{
+    keymap::{Action, Keymap},
+    theme::ThemeName,
+};
+
+// Example config.toml:
+//
+// theme = "light"
+//
+// [keys]
+// select_next = ["n", "Down"]
+// select_previous = "e"
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub theme: Option<ThemeName>,
+    keys: HashMap<Action, Keys>,
+}
+
+// A single key or a list of them
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Keys {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Config {
+    pub fn default_path() -> Option<PathBuf> {
+        Some(
+            dirs::config_dir()?
+                .join("combat-tracker")
+                .join("config.toml"),
+        )
+    }
+
+    /// A missing file is the same as an empty one
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Config::default()),
+            Err(err) => return Err(err),
+        };
+
+        toml::from_str(&text).map_err(|err| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), err),
+            )
+        })
+    }
+
+    pub fn keymap(&self) -> io::Result<Keymap> {
+        let overrides = self
+            .keys
+            .iter()
+            .map(|(action, keys)| {
+                let keys = match keys {
+                    Keys::One(key) => vec![key.clone()],
+                    Keys::Many(keys) => keys.clone(),
+                };
+                (*action, keys)
+            })
+            .collect();
+        Keymap::with_overrides(&overrides)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::crossterm::event::KeyCode;
+
+    use super::*;
+
+    #[test]
+    fn theme_and_keys_are_read() {
+        let config: Config = toml::from_str(
+            r#"
+            theme = "high-contrast"
+
+            [keys]
+            select_next = ["n", "Down"]
+            select_previous = "e"
+            "#,
+        )
+        .unwrap();
+        let keymap = config.keymap().unwrap();
+
+        assert_eq!(config.theme, Some(ThemeName::HighContrast));
+        assert_eq!(keymap.action(KeyCode::Down), Some(Action::SelectNext));
+        assert_eq!(
+            keymap.action(KeyCode::Char('e')),
+            Some(Action::SelectPrevious)
+        );
+    }
+
+    #[test]
+    fn unknown_actions_are_rejected() {
+        assert!(toml::from_str::<Config>("[keys]\nfly = \"x\"").is_err());
+        assert!(toml::from_str::<Config>("[colors]").is_err());
+    }
+}
@@ -0,0 +1,537 @@
+use std::{fmt::Display, str::FromStr};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::dice::{Expression, Roll};
+
+// Pathfinder 2e (remaster) damage types
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DamageType {
+    Bludgeoning,
+    Piercing,
+    Slashing,
+    Acid,
+    Cold,
+    Electricity,
+    Fire,
+    Force,
+    Sonic,
+    Vitality,
+    Void,
+    Bleed,
+    Mental,
+    Poison,
+    Spirit,
+    Precision,
+}
+
+impl DamageType {
+    pub const ALL: [DamageType; 16] = [
+        DamageType::Bludgeoning,
+        DamageType::Piercing,
+        DamageType::Slashing,
+        DamageType::Acid,
+        DamageType::Cold,
+        DamageType::Electricity,
+        DamageType::Fire,
+        DamageType::Force,
+        DamageType::Sonic,
+        DamageType::Vitality,
+        DamageType::Void,
+        DamageType::Bleed,
+        DamageType::Mental,
+        DamageType::Poison,
+        DamageType::Spirit,
+        DamageType::Precision,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DamageType::Bludgeoning => "bludgeoning",
+            DamageType::Piercing => "piercing",
+            DamageType::Slashing => "slashing",
+            DamageType::Acid => "acid",
+            DamageType::Cold => "cold",
+            DamageType::Electricity => "electricity",
+            DamageType::Fire => "fire",
+            DamageType::Force => "force",
+            DamageType::Sonic => "sonic",
+            DamageType::Vitality => "vitality",
+            DamageType::Void => "void",
+            DamageType::Bleed => "bleed",
+            DamageType::Mental => "mental",
+            DamageType::Poison => "poison",
+            DamageType::Spirit => "spirit",
+            DamageType::Precision => "precision",
+        }
+    }
+
+    /// Also takes the names from before the remaster
+    pub fn parse(word: &str) -> Option<Self> {
+        let word = word.to_lowercase();
+        match word.as_str() {
+            "positive" => Some(DamageType::Vitality),
+            "negative" => Some(DamageType::Void),
+            _ => DamageType::ALL
+                .into_iter()
+                .find(|damage_type| damage_type.name() == word),
+        }
+    }
+
+    pub fn is_physical(&self) -> bool {
+        matches!(
+            self,
+            DamageType::Bludgeoning | DamageType::Piercing | DamageType::Slashing
+        )
+    }
+
+    pub fn is_energy(&self) -> bool {
+        matches!(
+            self,
+            DamageType::Acid
+                | DamageType::Cold
+                | DamageType::Electricity
+                | DamageType::Fire
+                | DamageType::Force
+                | DamageType::Sonic
+                | DamageType::Vitality
+                | DamageType::Void
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DamageKind {
+    // None for untyped damage
+    pub damage_type: Option<DamageType>,
+    pub persistent: bool,
+}
+
+impl Display for DamageKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.persistent, self.damage_type) {
+            (true, Some(damage_type)) => write!(f, "persistent {}", damage_type.name()),
+            (true, None) => write!(f, "persistent"),
+            (false, Some(damage_type)) => write!(f, "{}", damage_type.name()),
+            (false, None) => Ok(()),
+        }
+    }
+}
+
+/// Damage as typed, like `2d6+4 slashing, 1d6 persistent fire`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DamageExpression {
+    parts: Vec<(Expression, DamageKind)>,
+}
+
+impl DamageExpression {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut parts = vec![];
+        for part in source.split(',').filter(|part| !part.trim().is_empty()) {
+            // Dice and numbers can't spell out a damage type, so the words are everything else
+            let (dice, words): (Vec<&str>, Vec<&str>) = part.split_whitespace().partition(|word| {
+                word.chars()
+                    .all(|ch| ch.is_ascii_digit() || "dkhl+-".contains(ch))
+            });
+            let expression = Expression::parse(&dice.join(" ")).map_err(|err| err.to_string())?;
+
+            let mut kind = DamageKind::default();
+            for word in words {
+                if word.eq_ignore_ascii_case("persistent") {
+                    kind.persistent = true;
+                    continue;
+                }
+                match (DamageType::parse(word), kind.damage_type) {
+                    (Some(damage_type), None) => kind.damage_type = Some(damage_type),
+                    (Some(_), Some(_)) => {
+                        return Err(format!("'{}' has more than one type", part.trim()))
+                    }
+                    (None, _) => return Err(format!("'{}' is not a damage type", word)),
+                }
+            }
+            parts.push((expression, kind));
+        }
+
+        if parts.is_empty() {
+            return Err("empty expression".into());
+        }
+        Ok(DamageExpression { parts })
+    }
+
+    /// Rolls the parts dealt right away, the rolls come in the same order as the parts of the damage.
+    /// Persistent parts are kept as dice, they get rolled at the end of each turn.
+    pub fn roll(&self, rng: &mut impl Rng) -> (Damage, Vec<Roll>) {
+        let mut damage = Damage::default();
+        let mut rolls = vec![];
+        for (expression, kind) in &self.parts {
+            if kind.persistent {
+                damage.persistent.push(PersistentDamage {
+                    dice: expression.to_string(),
+                    damage_type: kind.damage_type,
+                });
+                continue;
+            }
+            let roll = expression.roll(rng);
+            damage.parts.push((roll.total.max(0) as u32, *kind));
+            rolls.push(roll);
+        }
+        (damage, rolls)
+    }
+
+    /// Whether the parts rolled right away are plain numbers
+    pub fn is_constant(&self) -> bool {
+        self.parts
+            .iter()
+            .filter(|(_, kind)| !kind.persistent)
+            .all(|(expression, _)| expression.is_constant())
+    }
+}
+
+/// Rolled damage, one amount per type, and any persistent damage it starts
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Damage {
+    pub parts: Vec<(u32, DamageKind)>,
+    pub persistent: Vec<PersistentDamage>,
+}
+
+impl Damage {
+    pub fn untyped(amount: u32) -> Self {
+        Damage {
+            parts: vec![(amount, DamageKind::default())],
+            ..Default::default()
+        }
+    }
+
+    pub fn total(&self) -> u32 {
+        self.parts.iter().map(|(amount, _)| amount).sum()
+    }
+
+    /// The same types with every amount changed, like halved on a successful save
+    pub fn scaled(&self, scale: impl Fn(u32) -> u32) -> Self {
+        Damage {
+            parts: self
+                .parts
+                .iter()
+                .map(|(amount, kind)| (scale(*amount), *kind))
+                .collect(),
+            persistent: self.persistent.clone(),
+        }
+    }
+}
+
+impl Display for Damage {
+    /// Like `12 fire, 3, 1d6 persistent bleed`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let parts: Vec<String> = self
+            .parts
+            .iter()
+            .map(|(amount, kind)| format!("{} {}", amount, kind).trim_end().to_string())
+            .chain(self.persistent.iter().map(PersistentDamage::to_string))
+            .collect();
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+/// Damage taken at the end of every turn until a flat check ends it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PersistentDamage {
+    // Dice expression, rolled again every turn
+    pub dice: String,
+    pub damage_type: Option<DamageType>,
+}
+
+impl PersistentDamage {
+    pub const FLAT_CHECK_DC: i32 = 15;
+
+    pub fn kind(&self) -> DamageKind {
+        DamageKind {
+            damage_type: self.damage_type,
+            persistent: true,
+        }
+    }
+
+    pub fn roll(&self, rng: &mut impl Rng) -> Damage {
+        let amount = Expression::parse(&self.dice)
+            .map(|expression| expression.roll(rng).total.max(0) as u32)
+            .unwrap_or_default();
+        Damage {
+            parts: vec![(amount, self.kind())],
+            ..Default::default()
+        }
+    }
+
+    pub fn average(&self) -> f64 {
+        Expression::parse(&self.dice)
+            .map(|expression| expression.average())
+            .unwrap_or_default()
+    }
+}
+
+impl Display for PersistentDamage {
+    /// Like `1d6 persistent fire`, the way it's typed
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.dice, self.kind())
+    }
+}
+
+/// What an immunity, weakness or resistance is to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Against {
+    Type(DamageType),
+    Physical,
+    Energy,
+    All,
+    // Things the tracker can't tell from the damage, like cold iron or area damage.
+    // Shown with the rest but never applied.
+    Other(String),
+}
+
+impl Against {
+    pub fn parse(text: &str) -> Self {
+        let text = text.trim().to_lowercase();
+        match text.as_str() {
+            "physical" => Against::Physical,
+            "energy" => Against::Energy,
+            "all" | "all damage" => Against::All,
+            _ => DamageType::parse(&text)
+                .map(Against::Type)
+                .unwrap_or(Against::Other(text)),
+        }
+    }
+
+    pub fn matches(&self, kind: &DamageKind) -> bool {
+        match self {
+            Against::Type(damage_type) => kind.damage_type == Some(*damage_type),
+            Against::Physical => kind.damage_type.is_some_and(|kind| kind.is_physical()),
+            Against::Energy => kind.damage_type.is_some_and(|kind| kind.is_energy()),
+            Against::All => true,
+            Against::Other(_) => false,
+        }
+    }
+}
+
+impl Display for Against {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Against::Type(damage_type) => write!(f, "{}", damage_type.name()),
+            Against::Physical => write!(f, "physical"),
+            Against::Energy => write!(f, "energy"),
+            Against::All => write!(f, "all"),
+            Against::Other(text) => write!(f, "{}", text),
+        }
+    }
+}
+
+/// Immunities, weaknesses and resistances.
+/// Written like `immune poison, precision; weak fire 5; resist physical 5`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Adjustments {
+    pub immunities: Vec<Against>,
+    pub weaknesses: Vec<(Against, u32)>,
+    pub resistances: Vec<(Against, u32)>,
+}
+
+impl Adjustments {
+    pub fn is_empty(&self) -> bool {
+        self.immunities.is_empty() && self.weaknesses.is_empty() && self.resistances.is_empty()
+    }
+
+    pub fn immunity(&self, kind: &DamageKind) -> Option<&Against> {
+        self.immunities.iter().find(|against| against.matches(kind))
+    }
+
+    /// Damage left after immunities, then weaknesses, then resistances, each type on its own.
+    /// Also returns which of them applied.
+    pub fn apply(&self, damage: &Damage) -> (u32, Vec<String>) {
+        let mut total: u32 = 0;
+        let mut applied = vec![];
+        for (amount, kind) in &damage.parts {
+            // No damage means nothing to be weak to
+            if *amount == 0 {
+                continue;
+            }
+            if let Some(against) = self.immunity(kind) {
+                applied.push(format!("immune {}", against));
+                continue;
+            }
+
+            // Only the highest weakness and resistance count
+            let highest = |list: &[(Against, u32)]| {
+                list.iter()
+                    .filter(|(against, _)| against.matches(kind))
+                    .max_by_key(|(_, value)| *value)
+                    .cloned()
+            };
+            let mut amount = *amount;
+            if let Some((against, value)) = highest(&self.weaknesses) {
+                amount = amount.saturating_add(value);
+                applied.push(format!("weak {} {}", against, value));
+            }
+            if let Some((against, value)) = highest(&self.resistances) {
+                amount = amount.saturating_sub(value);
+                applied.push(format!("resist {} {}", against, value));
+            }
+            total = total.saturating_add(amount);
+        }
+        (total, applied)
+    }
+}
+
+impl FromStr for Adjustments {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut adjustments = Adjustments::default();
+        // Entries after a keyword share it, like the fire in `weak cold 5, fire 5`
+        let mut keyword = None;
+        for entry in text
+            .split([',', ';'])
+            .filter(|entry| !entry.trim().is_empty())
+        {
+            let mut words: Vec<&str> = entry.split_whitespace().collect();
+            let first = words[0].to_lowercase();
+            if ["immune", "immunity", "immunities"].contains(&first.as_str()) {
+                keyword = Some(Keyword::Immune);
+                words.remove(0);
+            } else if ["weak", "weakness", "weaknesses"].contains(&first.as_str()) {
+                keyword = Some(Keyword::Weak);
+                words.remove(0);
+            } else if ["resist", "resistance", "resistances"].contains(&first.as_str()) {
+                keyword = Some(Keyword::Resist);
+                words.remove(0);
+            }
+            let Some(keyword) = keyword else {
+                return Err(format!(
+                    "'{}' needs immune, weak or resist in front",
+                    entry.trim()
+                ));
+            };
+
+            if keyword == Keyword::Immune {
+                if words.is_empty() {
+                    return Err("immune to what?".into());
+                }
+                adjustments
+                    .immunities
+                    .push(Against::parse(&words.join(" ")));
+                continue;
+            }
+
+            // The value can go on either end, like `weak 5 fire` or `weak fire 5`
+            let value = if let Some(value) = words.last().and_then(|word| word.parse().ok()) {
+                words.pop();
+                value
+            } else if let Some(value) = words.first().and_then(|word| word.parse().ok()) {
+                words.remove(0);
+                value
+            } else {
+                return Err(format!("'{}' needs a value", entry.trim()));
+            };
+            if words.is_empty() {
+                return Err(format!("'{}' needs a damage type", entry.trim()));
+            }
+            let against = Against::parse(&words.join(" "));
+            match keyword {
+                Keyword::Weak => adjustments.weaknesses.push((against, value)),
+                _ => adjustments.resistances.push((against, value)),
+            }
+        }
+        Ok(adjustments)
+    }
+}
+
+impl Display for Adjustments {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let with_values = |list: &[(Against, u32)]| {
+            list.iter()
+                .map(|(against, value)| format!("{} {}", against, value))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let mut groups = vec![];
+        if !self.immunities.is_empty() {
+            let immunities: Vec<String> = self.immunities.iter().map(Against::to_string).collect();
+            groups.push(format!("immune {}", immunities.join(", ")));
+        }
+        if !self.weaknesses.is_empty() {
+            groups.push(format!("weak {}", with_values(&self.weaknesses)));
+        }
+        if !self.resistances.is_empty() {
+            groups.push(format!("resist {}", with_values(&self.resistances)));
+        }
+        write!(f, "{}", groups.join("; "))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Keyword {
+    Immune,
+    Weak,
+    Resist,
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    fn damage(source: &str) -> Damage {
+        DamageExpression::parse(source)
+            .unwrap()
+            .roll(&mut StdRng::seed_from_u64(0))
+            .0
+    }
+
+    #[test]
+    fn damage_is_split_by_type() {
+        assert_eq!(damage("12 fire").to_string(), "12 fire");
+        assert_eq!(
+            damage("4+2 slashing, 3 persistent bleed, 1").to_string(),
+            "6 slashing, 1, 3 persistent bleed"
+        );
+        assert_eq!(
+            damage("1d6 persistent fire").to_string(),
+            "1d6 persistent fire"
+        );
+        assert_eq!(damage("2 negative").to_string(), "2 void");
+        assert!(DamageExpression::parse("12 frie").is_err());
+        assert!(DamageExpression::parse("12 fire cold").is_err());
+        assert!(DamageExpression::parse(",").is_err());
+    }
+
+    #[test]
+    fn immunities_then_highest_weakness_then_highest_resistance() {
+        let adjustments: Adjustments =
+            "immune precision; weak fire 5, 2 energy, cold iron 5; resist physical 3, all 1"
+                .parse()
+                .unwrap();
+        assert_eq!(
+            adjustments.to_string(),
+            "immune precision; weak fire 5, energy 2, cold iron 5; resist physical 3, all 1"
+        );
+
+        assert_eq!(adjustments.apply(&damage("12 fire")).0, 16);
+        assert_eq!(adjustments.apply(&damage("8 slashing, 4 precision")).0, 5);
+        assert_eq!(adjustments.apply(&damage("2 piercing")).0, 0);
+        assert_eq!(adjustments.apply(&damage("0 fire")).0, 0);
+        assert_eq!(adjustments.apply(&damage("6")).0, 5);
+        assert_eq!(
+            adjustments.apply(&damage("12 fire")).1,
+            ["weak fire 5", "resist all 1"]
+        );
+
+        assert!("fire 5".parse::<Adjustments>().is_err());
+        assert!("weak fire".parse::<Adjustments>().is_err());
+        assert_eq!("".parse::<Adjustments>(), Ok(Adjustments::default()));
+    }
+
+    #[test]
+    fn huge_weaknesses_saturate() {
+        let adjustments: Adjustments = "weak fire 3000000000, cold 3000000000".parse().unwrap();
+        assert_eq!(adjustments.apply(&damage("1 fire")).0, 3_000_000_001);
+        assert_eq!(adjustments.apply(&damage("1 fire, 1 cold")).0, u32::MAX);
+    }
+}
@@ -0,0 +1,387 @@
+use std::fmt::Display;
+
+use rand::Rng;
+
+// Keeps a typo like 1000000d6 from freezing the app
+const MAX_DICE: u32 = 1000;
+// Way past anything a game needs, and small enough that totals can't overflow
+const MAX_SIDES: u32 = 1000;
+const MAX_CONSTANT: u32 = 1_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Keep {
+    All,
+    Highest(u32),
+    Lowest(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Term {
+    Constant(i32),
+    Dice { count: u32, sides: u32, keep: Keep },
+}
+
+/// A parsed dice expression like `2d8+4`, `4d6kh3` or `1d20+12`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expression {
+    source: String,
+    // Sign and term, the sign is -1 for subtracted terms
+    terms: Vec<(i32, Term)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError(String);
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Expression {
+    pub fn parse(source: &str) -> Result<Self, ParseError> {
+        let compact: String = source
+            .chars()
+            .filter(|ch| !ch.is_whitespace())
+            .collect::<String>()
+            .to_lowercase();
+        if compact.is_empty() {
+            return Err(ParseError("empty expression".into()));
+        }
+
+        let mut terms = vec![];
+        let mut rest = compact.as_str();
+        let mut sign = 1;
+        if let Some(stripped) = rest.strip_prefix('-') {
+            sign = -1;
+            rest = stripped;
+        } else if let Some(stripped) = rest.strip_prefix('+') {
+            rest = stripped;
+        }
+
+        loop {
+            let end = rest.find(['+', '-']).unwrap_or(rest.len());
+            terms.push((sign, parse_term(&rest[..end])?));
+
+            if end == rest.len() {
+                break;
+            }
+            sign = if rest[end..].starts_with('-') { -1 } else { 1 };
+            rest = &rest[end + 1..];
+        }
+
+        Ok(Expression {
+            source: compact,
+            terms,
+        })
+    }
+
+    pub fn roll(&self, rng: &mut impl Rng) -> Roll {
+        let mut total: i32 = 0;
+        let mut parts = vec![];
+
+        for (sign, term) in &self.terms {
+            match *term {
+                Term::Constant(value) => {
+                    total = total.saturating_add(sign * value);
+                    parts.push(RollPart::Constant(sign * value));
+                }
+                Term::Dice { count, sides, keep } => {
+                    let rolls: Vec<u32> = (0..count).map(|_| rng.random_range(1..=sides)).collect();
+
+                    // Which of the rolls count towards the total
+                    let mut order: Vec<usize> = (0..rolls.len()).collect();
+                    order.sort_by_key(|index| rolls[*index]);
+                    let kept: Vec<usize> = match keep {
+                        Keep::All => order,
+                        Keep::Highest(amount) => {
+                            order.into_iter().rev().take(amount as usize).collect()
+                        }
+                        Keep::Lowest(amount) => order.into_iter().take(amount as usize).collect(),
+                    };
+
+                    let sum: i32 = kept.iter().map(|index| rolls[*index] as i32).sum();
+                    total = total.saturating_add(sign * sum);
+                    parts.push(RollPart::Dice {
+                        negative: *sign < 0,
+                        rolls: rolls
+                            .iter()
+                            .enumerate()
+                            .map(|(index, roll)| (*roll, kept.contains(&index)))
+                            .collect(),
+                    });
+                }
+            }
+        }
+
+        Roll {
+            expression: self.source.clone(),
+            total,
+            parts,
+        }
+    }
+
+    /// Expected total, counting only the kept dice of `kh` and `kl` as if they were rolled alone.
+    /// Close enough for telling which of two expressions is bigger.
+    pub fn average(&self) -> f64 {
+        self.terms
+            .iter()
+            .map(|(sign, term)| {
+                let value = match *term {
+                    Term::Constant(value) => value as f64,
+                    Term::Dice { count, sides, keep } => {
+                        let kept = match keep {
+                            Keep::All => count,
+                            Keep::Highest(amount) | Keep::Lowest(amount) => amount.min(count),
+                        };
+                        kept as f64 * (sides as f64 + 1.0) / 2.0
+                    }
+                };
+                *sign as f64 * value
+            })
+            .sum()
+    }
+
+    /// Plain numbers don't need a breakdown of the roll
+    pub fn is_constant(&self) -> bool {
+        self.terms
+            .iter()
+            .all(|(_, term)| matches!(term, Term::Constant(_)))
+    }
+}
+
+impl Display for Expression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+fn parse_term(term: &str) -> Result<Term, ParseError> {
+    let number = |text: &str| {
+        if text.is_empty() {
+            return Err(ParseError("missing a number".into()));
+        }
+        text.parse::<u32>()
+            .map_err(|_| ParseError(format!("'{}' is not a number", text)))
+    };
+
+    let Some((count, rest)) = term.split_once('d') else {
+        let value = number(term)?;
+        if value > MAX_CONSTANT {
+            return Err(ParseError(format!(
+                "numbers can't be bigger than {}",
+                MAX_CONSTANT
+            )));
+        }
+        return Ok(Term::Constant(value as i32));
+    };
+
+    // d20 is shorthand for 1d20
+    let count = if count.is_empty() { 1 } else { number(count)? };
+    let (sides, keep) = if let Some((sides, amount)) = rest.split_once("kh") {
+        (sides, Keep::Highest(number(amount)?))
+    } else if let Some((sides, amount)) = rest.split_once("kl") {
+        (sides, Keep::Lowest(number(amount)?))
+    } else {
+        (rest, Keep::All)
+    };
+    let sides = number(sides)?;
+
+    if sides == 0 {
+        return Err(ParseError("dice need at least one side".into()));
+    }
+    if sides > MAX_SIDES {
+        return Err(ParseError(format!(
+            "dice can't have more than {} sides",
+            MAX_SIDES
+        )));
+    }
+    if count > MAX_DICE {
+        return Err(ParseError(format!(
+            "can't roll more than {} dice",
+            MAX_DICE
+        )));
+    }
+
+    Ok(Term::Dice { count, sides, keep })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum RollPart {
+    Constant(i32),
+    // Every die rolled and whether it was kept
+    Dice {
+        negative: bool,
+        rolls: Vec<(u32, bool)>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Roll {
+    pub expression: String,
+    pub total: i32,
+    parts: Vec<RollPart>,
+}
+
+impl Display for Roll {
+    /// Like `4d6kh3+2: [6, 5, (1), 3] + 2 = 16`, dropped dice in parentheses
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: ", self.expression)?;
+        for (index, part) in self.parts.iter().enumerate() {
+            let negative = match part {
+                RollPart::Constant(value) => *value < 0,
+                RollPart::Dice { negative, .. } => *negative,
+            };
+            match (index, negative) {
+                (0, true) => write!(f, "-")?,
+                (0, false) => {}
+                (_, true) => write!(f, " - ")?,
+                (_, false) => write!(f, " + ")?,
+            }
+
+            match part {
+                RollPart::Constant(value) => write!(f, "{}", value.abs())?,
+                RollPart::Dice { rolls, .. } => {
+                    let rolls = rolls
+                        .iter()
+                        .map(|(roll, kept)| {
+                            if *kept {
+                                roll.to_string()
+                            } else {
+                                format!("({})", roll)
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    write!(f, "[{}]", rolls)?
+                }
+            }
+        }
+        write!(f, " = {}", self.total)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    fn roll(source: &str) -> Roll {
+        Expression::parse(source)
+            .unwrap()
+            .roll(&mut StdRng::seed_from_u64(0))
+    }
+
+    #[test]
+    fn expressions_are_parsed_into_terms() {
+        let expression = Expression::parse(" 2D8 + 4 - d4kh1 ").unwrap();
+        assert_eq!(expression.to_string(), "2d8+4-d4kh1");
+        assert_eq!(
+            expression.terms,
+            [
+                (
+                    1,
+                    Term::Dice {
+                        count: 2,
+                        sides: 8,
+                        keep: Keep::All
+                    }
+                ),
+                (1, Term::Constant(4)),
+                (
+                    -1,
+                    Term::Dice {
+                        count: 1,
+                        sides: 4,
+                        keep: Keep::Highest(1)
+                    }
+                ),
+            ]
+        );
+        assert!(!expression.is_constant());
+        assert!(Expression::parse("-3+5").unwrap().is_constant());
+    }
+
+    #[test]
+    fn signs_apply_to_each_term() {
+        assert_eq!(roll("-3+5").total, 2);
+        assert_eq!(roll("+7-10").total, -3);
+        assert_eq!(roll("10-1d1-1d1").total, 8);
+        assert_eq!(roll("-2d1").total, -2);
+    }
+
+    #[test]
+    fn only_the_kept_dice_count() {
+        let highest = roll("4d6kh3");
+        let lowest = roll("4d6kl1");
+        let RollPart::Dice { rolls, .. } = &highest.parts[0] else {
+            panic!("no dice rolled");
+        };
+        assert_eq!(rolls.iter().filter(|(_, kept)| *kept).count(), 3);
+        let dropped = rolls.iter().find(|(_, kept)| !kept).unwrap().0;
+        assert!(rolls.iter().all(|(roll, _)| *roll >= dropped));
+        let sum: u32 = rolls.iter().map(|(roll, _)| roll).sum();
+        assert_eq!(highest.total, (sum - dropped) as i32);
+
+        // Same seed, same dice, keeping only the smallest
+        assert_eq!(lowest.total, dropped as i32);
+    }
+
+    #[test]
+    fn bad_and_oversized_input_is_rejected() {
+        for source in [
+            "",
+            "d",
+            "2d",
+            "1d0",
+            "abc",
+            "1d6+",
+            "1001d6",
+            "1d1001",
+            "4000000000",
+            "1000001",
+            "2d6kh",
+        ] {
+            assert!(Expression::parse(source).is_err(), "{}", source);
+        }
+        assert!(Expression::parse("1000d1000+1000000").is_ok());
+    }
+
+    #[test]
+    fn huge_totals_saturate_instead_of_overflowing() {
+        let source = vec!["1000000"; 3000].join("+");
+        assert_eq!(roll(&source).total, i32::MAX);
+        let source = vec!["1000000"; 3000].join("-");
+        assert_eq!(roll(&format!("-{}", source)).total, i32::MIN);
+    }
+
+    #[test]
+    fn rolls_show_every_die() {
+        let roll = Roll {
+            expression: "4d6kh3-2".into(),
+            total: 12,
+            parts: vec![
+                RollPart::Dice {
+                    negative: false,
+                    rolls: vec![(6, true), (5, true), (1, false), (3, true)],
+                },
+                RollPart::Constant(-2),
+            ],
+        };
+        assert_eq!(roll.to_string(), "4d6kh3-2: [6, 5, (1), 3] - 2 = 12");
+
+        let roll = Roll {
+            expression: "-1d4+3".into(),
+            total: 1,
+            parts: vec![
+                RollPart::Dice {
+                    negative: true,
+                    rolls: vec![(2, true)],
+                },
+                RollPart::Constant(3),
+            ],
+        };
+        assert_eq!(roll.to_string(), "-1d4+3: -[2] + 3 = 1");
+    }
+}
@@ -0,0 +1,1189 @@
+use std::{cmp::Ordering, fmt::Display, ops::Range, str::FromStr};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    checks::{Check, Degree, SaveKind},
+    conditions::{self, Condition, ConditionKind},
+    damage::{Adjustments, Damage, PersistentDamage},
+    dice::{Expression, Roll},
+};
+
+// Everything about a fight that isn't how it's being looked at
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Encounter {
+    pub creatures: Vec<Creature>,
+    // Whose turn it is, None when combat hasn't started
+    #[serde(default)]
+    pub active_creature: Option<usize>,
+    #[serde(default)]
+    pub round: u32,
+    #[serde(default)]
+    pub tie_break: TieBreak,
+    #[serde(default)]
+    pub copies: CopyRules,
+    // Take creatures outside the party out of the list once they are defeated
+    #[serde(default)]
+    pub remove_defeated: bool,
+}
+
+impl Encounter {
+    pub fn new(creatures: Vec<Creature>) -> Self {
+        Encounter {
+            creatures,
+            ..Default::default()
+        }
+    }
+
+    /// Adds the creature to the end of the list, returns its index
+    pub fn add(&mut self, creature: Creature) -> usize {
+        self.creatures.push(creature);
+        self.creatures.len() - 1
+    }
+
+    /// Inserts numbered copies below the original and any copies right under it.
+    /// Returns the indices of the new copies, None if there is no such creature
+    /// or the numbering has run out of tags for that many.
+    pub fn duplicate(
+        &mut self,
+        index: usize,
+        count: usize,
+        rng: &mut impl Rng,
+    ) -> Option<Range<usize>> {
+        let original = self.creatures.get(index)?.clone();
+        let rules = self.copies;
+        let (base, _) = rules.numbering.split(&original.name);
+        // Carry on after the highest copy so far, the original counts as the first
+        let highest = self
+            .creatures
+            .iter()
+            .map(|creature| rules.numbering.split(&creature.name))
+            .filter(|(other, _)| *other == base)
+            .map(|(_, number)| number)
+            .max()
+            .unwrap_or(1);
+        let count_tags = u32::try_from(count).ok()?;
+        highest
+            .checked_add(count_tags)
+            .filter(|last| *last <= rules.numbering.last())?;
+
+        let copies: Vec<Creature> = (highest + 1..=highest + count_tags)
+            .map(|number| {
+                let mut copy = original.clone();
+                copy.name = format!("{} {}", base, rules.numbering.tag(number))
+                    .trim_start()
+                    .to_string();
+                if rules.reroll_health {
+                    if let Some(expression) = copy
+                        .health_dice
+                        .as_deref()
+                        .and_then(|dice| Expression::parse(dice).ok())
+                    {
+                        copy.max_health = expression.roll(rng).total;
+                        copy.health = copy.max_health;
+                    }
+                }
+                if rules.reroll_initiative && copy.initiative.is_some() {
+                    copy.initiative = Some(copy.roll_initiative(rng).total);
+                }
+                copy
+            })
+            .collect();
+
+        let mut insert_at = index + 1;
+        while self
+            .creatures
+            .get(insert_at)
+            .is_some_and(|creature| rules.numbering.split(&creature.name).0 == base)
+        {
+            insert_at += 1;
+        }
+        self.creatures.splice(insert_at..insert_at, copies);
+        if let Some(active) = self.active_creature.as_mut() {
+            if *active >= insert_at {
+                *active += count;
+            }
+        }
+        Some(insert_at..insert_at + count)
+    }
+
+    pub fn delete(&mut self, index: usize) -> Option<Creature> {
+        if index >= self.creatures.len() {
+            return None;
+        }
+
+        // Removing the active creature passes the turn to the next one in initiative order
+        let mut active = self.active_creature;
+        if active == Some(index) {
+            let order = self.initiative_order();
+            let position = order.iter().position(|other| *other == index).unwrap();
+            active = match order.get(position + 1) {
+                Some(next) => Some(*next),
+                None if order.len() > 1 => {
+                    self.round += 1;
+                    Some(order[0])
+                }
+                None => None,
+            };
+        }
+
+        let creature = self.creatures.remove(index);
+        self.active_creature =
+            active.map(|active| if active > index { active - 1 } else { active });
+        if self.creatures.is_empty() {
+            self.round = 0;
+        }
+        Some(creature)
+    }
+
+    /// Indices of the defeated creatures that aren't in the party
+    pub fn defeated(&self) -> Vec<usize> {
+        self.creatures
+            .iter()
+            .enumerate()
+            .filter(|(_, creature)| creature.dead && creature.side != Side::Party)
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    pub fn damage(&mut self, index: usize, amount: u32) {
+        if let Some(creature) = self.creatures.get_mut(index) {
+            creature.apply_health_shift(HealthShift::Decrease(amount));
+        }
+    }
+
+    pub fn heal(&mut self, index: usize, amount: u32) {
+        if let Some(creature) = self.creatures.get_mut(index) {
+            creature.apply_health_shift(HealthShift::Increase(amount));
+        }
+    }
+
+    /// Stable sort that keeps the turn with the same creature.
+    /// Returns the new index of every creature, indexed by its old one.
+    pub fn sort_by(&mut self, compare: impl Fn(&Creature, &Creature) -> Ordering) -> Vec<usize> {
+        // Sort indices instead of the creatures so the turn can follow along
+        let mut order: Vec<usize> = (0..self.creatures.len()).collect();
+        order.sort_by(|a, b| compare(&self.creatures[*a], &self.creatures[*b]));
+
+        let mut new_indices = vec![0; order.len()];
+        for (new, old) in order.iter().enumerate() {
+            new_indices[*old] = new;
+        }
+        self.active_creature = self.active_creature.map(|active| new_indices[active]);
+        self.creatures = order
+            .iter()
+            .map(|index| self.creatures[*index].clone())
+            .collect();
+        new_indices
+    }
+
+    pub fn sort_by_initiative(&mut self, descending: bool) -> Vec<usize> {
+        // Creatures without initiative sort as the lowest
+        let tie_break = self.tie_break;
+        if descending {
+            self.sort_by(|a, b| tie_break.turn_order(a, b))
+        } else {
+            self.sort_by(|a, b| tie_break.turn_order(b, a))
+        }
+    }
+
+    /// Indices of the creatures in the order they take their turns
+    pub fn initiative_order(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.creatures.len()).collect();
+        // Stable sort, so list order settles whatever the tie break doesn't
+        order.sort_by(|a, b| {
+            self.tie_break
+                .turn_order(&self.creatures[*a], &self.creatures[*b])
+        });
+        order
+    }
+
+    /// Swaps two creatures in the list, the turn stays with the same creature
+    pub fn swap(&mut self, a: usize, b: usize) {
+        self.creatures.swap(a, b);
+        self.active_creature = self.active_creature.map(|active| match active {
+            active if active == a => b,
+            active if active == b => a,
+            active => active,
+        });
+    }
+
+    /// Rolls d20 + modifier for everyone without an initiative, returns who rolled what
+    pub fn roll_initiative(&mut self, rng: &mut impl Rng) -> Vec<(usize, Roll)> {
+        let mut results = vec![];
+        for (index, creature) in self
+            .creatures
+            .iter_mut()
+            .enumerate()
+            .filter(|(_, creature)| creature.initiative.is_none())
+        {
+            let roll = creature.roll_initiative(rng);
+            creature.initiative = Some(roll.total);
+            results.push((index, roll));
+        }
+        results
+    }
+
+    /// Ends the active creature's turn and passes it on.
+    /// Returns what changed on the creature whose turn ended.
+    pub fn next_turn(&mut self) -> Vec<String> {
+        if self.creatures.is_empty() {
+            return vec![];
+        }
+
+        let mut changes = vec![];
+        if let Some(creature) = self
+            .active_creature
+            .and_then(|index| self.creatures.get_mut(index))
+        {
+            changes = conditions::end_of_turn(&mut creature.conditions)
+                .into_iter()
+                .map(|change| format!("{}: {}", creature.name, change))
+                .collect();
+        }
+
+        let order = self.initiative_order();
+        let position = self
+            .active_creature
+            .and_then(|active| order.iter().position(|index| *index == active));
+        self.active_creature = Some(match position {
+            None => {
+                // Start of combat
+                self.round = 1;
+                order[0]
+            }
+            Some(position) if position + 1 >= order.len() => {
+                self.round += 1;
+                order[0]
+            }
+            Some(position) => order[position + 1],
+        });
+
+        changes
+    }
+
+    pub fn previous_turn(&mut self) {
+        let order = self.initiative_order();
+        let position = self
+            .active_creature
+            .and_then(|active| order.iter().position(|index| *index == active));
+        self.active_creature = match position {
+            None => None,
+            Some(0) if self.round <= 1 => {
+                // Stepping back from the first turn takes us out of combat
+                self.round = 0;
+                None
+            }
+            Some(0) => {
+                self.round -= 1;
+                order.last().copied()
+            }
+            Some(position) => Some(order[position - 1]),
+        };
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Side {
+    #[default]
+    Enemy,
+    // Player characters
+    Party,
+    // On the party's side but run by the GM, like hirelings and summons
+    Ally,
+    // Bystanders that nobody is fighting yet
+    Neutral,
+}
+
+impl Side {
+    pub const ALL: [Side; 4] = [Side::Enemy, Side::Party, Side::Ally, Side::Neutral];
+
+    pub fn next(&self) -> Self {
+        match self {
+            Side::Enemy => Side::Party,
+            Side::Party => Side::Ally,
+            Side::Ally => Side::Neutral,
+            Side::Neutral => Side::Enemy,
+        }
+    }
+
+    /// Fighting with the party, so the players get to know how it's doing
+    pub fn is_friendly(&self) -> bool {
+        matches!(self, Side::Party | Side::Ally)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Side::Enemy => "enemies",
+            Side::Party => "party",
+            Side::Ally => "allies",
+            Side::Neutral => "neutrals",
+        }
+    }
+
+    // The GM's creatures go before the players' on a tie, foes first of all
+    fn tie_order(&self) -> u8 {
+        match self {
+            Side::Enemy => 0,
+            Side::Neutral => 1,
+            Side::Ally => 2,
+            Side::Party => 3,
+        }
+    }
+}
+
+/// How to order creatures with the same initiative
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TieBreak {
+    // Enemies act before the party, as the PF2e rules say
+    #[default]
+    Side,
+    // Higher initiative modifier acts first
+    Modifier,
+    // Whatever order the creatures are listed in, rearranged by hand
+    Manual,
+}
+
+impl TieBreak {
+    pub fn next(&self) -> Self {
+        match self {
+            TieBreak::Side => TieBreak::Modifier,
+            TieBreak::Modifier => TieBreak::Manual,
+            TieBreak::Manual => TieBreak::Side,
+        }
+    }
+
+    pub fn describe(&self) -> &'static str {
+        match self {
+            TieBreak::Side => "enemies first",
+            TieBreak::Modifier => "higher modifier first",
+            TieBreak::Manual => "list order",
+        }
+    }
+
+    /// Less means `a` acts before `b`. Equal is left for list order to settle.
+    pub fn turn_order(&self, a: &Creature, b: &Creature) -> Ordering {
+        b.initiative.cmp(&a.initiative).then_with(|| match self {
+            TieBreak::Side => a.side.tie_order().cmp(&b.side.tie_order()),
+            TieBreak::Modifier => b.initiative_modifier.cmp(&a.initiative_modifier),
+            TieBreak::Manual => Ordering::Equal,
+        })
+    }
+}
+
+/// How copies of a creature are named and rolled
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CopyRules {
+    pub numbering: Numbering,
+    // Roll max health again from the dice it was set with
+    pub reroll_health: bool,
+    // Roll initiative again for copies of creatures that have one
+    pub reroll_initiative: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Numbering {
+    // Goblin 2, Goblin 3
+    #[default]
+    Numbers,
+    // Goblin B, Goblin C, after Z comes AA
+    Letters,
+}
+
+impl Numbering {
+    pub fn next(&self) -> Self {
+        match self {
+            Numbering::Numbers => Numbering::Letters,
+            Numbering::Letters => Numbering::Numbers,
+        }
+    }
+
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Numbering::Numbers => "Goblin 2",
+            Numbering::Letters => "Goblin B",
+        }
+    }
+
+    /// The tag of the nth copy, counting from 1
+    pub fn tag(&self, number: u32) -> String {
+        match self {
+            Numbering::Numbers => number.to_string(),
+            Numbering::Letters => {
+                let mut tag = vec![];
+                let mut number = number;
+                while number > 0 {
+                    number -= 1;
+                    tag.push((b'A' + (number % 26) as u8) as char);
+                    number /= 26;
+                }
+                tag.iter().rev().collect()
+            }
+        }
+    }
+
+    /// The highest number split can read back from a tag
+    pub fn last(&self) -> u32 {
+        match self {
+            Numbering::Numbers => u32::MAX,
+            // ZZ, since longer tags aren't told apart from the name
+            Numbering::Letters => 26 * 26 + 26,
+        }
+    }
+
+    /// Splits the tag off the name, a name without one is the first of its kind
+    pub fn split<'a>(&self, name: &'a str) -> (&'a str, u32) {
+        let Some((base, tag)) = name.rsplit_once(' ') else {
+            return (name, 1);
+        };
+        let number = match self {
+            Numbering::Numbers => tag.parse().ok(),
+            // Longer runs of capitals are more likely part of the name than a tag
+            Numbering::Letters
+                if tag.len() <= 2 && tag.chars().all(|ch| ch.is_ascii_uppercase()) =>
+            {
+                Some(
+                    tag.bytes()
+                        .fold(0, |number, letter| number * 26 + (letter - b'A') as u32 + 1),
+                )
+            }
+            Numbering::Letters => None,
+        };
+        match number {
+            Some(number) if number > 0 => (base, number),
+            _ => (name, 1),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HealthShift {
+    Increase(u32),
+    Decrease(u32),
+}
+
+impl HealthShift {
+    pub fn sign(&self) -> char {
+        match self {
+            HealthShift::Increase(_) => '+',
+            HealthShift::Decrease(_) => '-',
+        }
+    }
+
+    pub fn with_magnitude(&self, magnitude: u32) -> Self {
+        match self {
+            HealthShift::Increase(_) => HealthShift::Increase(magnitude),
+            HealthShift::Decrease(_) => HealthShift::Decrease(magnitude),
+        }
+    }
+}
+
+impl FromStr for HealthShift {
+    type Err = <i32 as FromStr>::Err;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let numeric: i32 = s.parse()?;
+
+        Ok(if numeric.is_positive() {
+            HealthShift::Increase(numeric.try_into().unwrap())
+        } else {
+            HealthShift::Decrease((-numeric).try_into().unwrap())
+        })
+    }
+}
+
+impl Display for HealthShift {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (sign_char, magnitude) = match self {
+            HealthShift::Increase(mag) => ('+', mag),
+            HealthShift::Decrease(mag) => ('-', mag),
+        };
+        write!(f, "{}{}", sign_char, magnitude)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Creature {
+    pub name: String,
+    pub health: i32,
+    // Zero when unknown
+    pub max_health: i32,
+    // Dice the max health was rolled with, so copies can roll their own
+    pub health_dice: Option<String>,
+    pub temp_health: u32,
+    // Zero when unknown
+    pub armor_class: i32,
+    pub saves: Saves,
+    pub perception: i32,
+    pub adjustments: Adjustments,
+    // None until set or rolled
+    pub initiative: Option<i32>,
+    pub initiative_modifier: i32,
+    pub side: Side,
+    pub conditions: Vec<Condition>,
+    pub persistent: Vec<PersistentDamage>,
+    // Dead characters and defeated creatures, greyed out but kept until removed
+    pub dead: bool,
+    pub notes: String,
+    pub notes_cursor_pos: (usize, usize),
+}
+
+impl Creature {
+    pub fn new(name: impl Into<String>) -> Self {
+        Creature {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Returns what happened if the creature went down or got back up, like `is dying 2`
+    pub fn apply_health_shift(&mut self, shift: HealthShift) -> Option<String> {
+        let before = self.health;
+        match shift {
+            HealthShift::Increase(mag) => {
+                // Healing can't go past the maximum, if we know it, but doesn't lower health
+                // that was already set above it
+                let healed = self.health.saturating_add_unsigned(mag);
+                self.health = if self.max_health > 0 {
+                    self.health.max(healed.min(self.max_health))
+                } else {
+                    healed
+                };
+            }
+            HealthShift::Decrease(mag) => {
+                // Temporary hit points soak damage first
+                let absorbed = mag.min(self.temp_health);
+                self.temp_health -= absorbed;
+                let taken = i32::try_from(mag - absorbed).unwrap_or(i32::MAX);
+                self.health = self.health.saturating_sub(taken);
+            }
+        }
+        self.settle_health(before)
+    }
+
+    /// Returns what happened if the creature went down or got back up, like `is dying 2`
+    pub fn set_health(&mut self, health: i32) -> Option<String> {
+        let before = self.health;
+        self.health = health;
+        self.settle_health(before)
+    }
+
+    /// The PF2e rules for going down and getting back up, after health changed from `before`
+    fn settle_health(&mut self, before: i32) -> Option<String> {
+        if self.side != Side::Party {
+            // Only the party gets to be dying, everyone else is out of the fight at 0.
+            // Hitting one that sits at 0, like one without health set, takes it out too.
+            let hurt = self.health < before;
+            return if self.health <= 0 && (before > 0 || hurt) && !self.dead {
+                self.dead = true;
+                Some("was defeated".into())
+            } else if self.health > 0 && self.dead {
+                self.dead = false;
+                Some("is back in the fight".into())
+            } else {
+                None
+            };
+        }
+        if self.dead {
+            return None;
+        }
+
+        let dying = conditions::value(&self.conditions, ConditionKind::Dying);
+        if self.health <= 0 {
+            let hurt = self.health < before;
+            // Characters don't go below 0
+            self.health = 0;
+            // Going down, or getting hit while down without dying
+            if before > 0 || (hurt && dying == 0) {
+                let wounded = conditions::value(&self.conditions, ConditionKind::Wounded);
+                Some(self.set_dying(1 + wounded))
+            } else if hurt {
+                Some(self.set_dying(dying + 1))
+            } else {
+                None
+            }
+        } else if before <= 0 {
+            let unconscious = conditions::value(&self.conditions, ConditionKind::Unconscious) > 0;
+            conditions::set(&mut self.conditions, ConditionKind::Unconscious, 0);
+            if dying > 0 {
+                Some(self.stop_dying())
+            } else if unconscious {
+                Some("woke up".into())
+            } else {
+                None
+            }
+        } else {
+            None
+        }
+    }
+
+    fn set_dying(&mut self, value: u8) -> String {
+        conditions::set(&mut self.conditions, ConditionKind::Dying, value);
+        conditions::set(&mut self.conditions, ConditionKind::Unconscious, 1);
+        // Doomed brings death closer
+        let doomed = conditions::value(&self.conditions, ConditionKind::Doomed);
+        if value >= 4u8.saturating_sub(doomed).max(1) {
+            self.dead = true;
+            "died".into()
+        } else {
+            format!("is dying {}", value)
+        }
+    }
+
+    fn stop_dying(&mut self) -> String {
+        conditions::set(&mut self.conditions, ConditionKind::Dying, 0);
+        let wounded = conditions::value(&self.conditions, ConditionKind::Wounded) + 1;
+        conditions::set(&mut self.conditions, ConditionKind::Wounded, wounded);
+        format!("is no longer dying, wounded {}", wounded)
+    }
+
+    pub fn is_dying(&self) -> bool {
+        !self.dead && conditions::value(&self.conditions, ConditionKind::Dying) > 0
+    }
+
+    /// DC of the flat check made at the start of a dying creature's turn
+    pub fn recovery_dc(&self) -> i32 {
+        10 + conditions::value(&self.conditions, ConditionKind::Dying) as i32
+    }
+
+    /// Moves the dying value by how the recovery check went. Returns what happened.
+    pub fn recover(&mut self, degree: Degree) -> String {
+        let dying = conditions::value(&self.conditions, ConditionKind::Dying);
+        let dying = match degree {
+            Degree::CriticalSuccess => dying.saturating_sub(2),
+            Degree::Success => dying.saturating_sub(1),
+            Degree::Failure => dying + 1,
+            Degree::CriticalFailure => dying + 2,
+        };
+        if dying == 0 {
+            // Still unconscious until healed
+            self.stop_dying()
+        } else {
+            self.set_dying(dying)
+        }
+    }
+
+    /// Deals the damage after immunities, weaknesses and resistances, and starts any persistent damage
+    pub fn take_damage(&mut self, damage: &Damage) -> DamageTaken {
+        let (amount, mut applied) = self.adjustments.apply(damage);
+        let health_change = self.apply_health_shift(HealthShift::Decrease(amount));
+        for persistent in &damage.persistent {
+            if let Some(against) = self.adjustments.immunity(&persistent.kind()) {
+                applied.push(format!("immune {}", against));
+            } else if self.add_persistent(persistent.clone()) {
+                applied.push(format!("plus {}", persistent));
+            }
+        }
+        DamageTaken {
+            amount,
+            applied,
+            health_change,
+        }
+    }
+
+    /// Persistent damage of a type the creature already has only replaces it when it's bigger.
+    /// Returns whether it was added.
+    pub fn add_persistent(&mut self, persistent: PersistentDamage) -> bool {
+        match self
+            .persistent
+            .iter_mut()
+            .find(|existing| existing.damage_type == persistent.damage_type)
+        {
+            Some(existing) if existing.average() >= persistent.average() => false,
+            Some(existing) => {
+                *existing = persistent;
+                true
+            }
+            None => {
+                self.persistent.push(persistent);
+                true
+            }
+        }
+    }
+
+    /// Rolls the persistent damage and a flat check to end each one
+    pub fn roll_persistent(&self, rng: &mut impl Rng) -> Vec<PersistentTick> {
+        self.persistent
+            .iter()
+            .map(|persistent| {
+                let check = Check::roll(0, PersistentDamage::FLAT_CHECK_DC, rng);
+                PersistentTick {
+                    persistent: persistent.clone(),
+                    damage: persistent.roll(rng),
+                    check,
+                    ends: check.degree >= Degree::Success,
+                }
+            })
+            .collect()
+    }
+
+    /// Deals the rolled persistent damage, then drops what ended. Returns what happened.
+    pub fn resolve_persistent(&mut self, ticks: &[PersistentTick]) -> Vec<String> {
+        let mut changes = vec![];
+        for tick in ticks {
+            let taken = self.take_damage(&tick.damage);
+            let mut change = format!("took {} {}", taken.amount, tick.persistent.kind());
+            if !taken.applied.is_empty() {
+                change = format!("{} ({})", change, taken.applied.join(", "));
+            }
+            changes.push(change);
+            changes.extend(taken.health_change);
+            if tick.ends {
+                self.persistent
+                    .retain(|persistent| *persistent != tick.persistent);
+                changes.push(format!("{} ended", tick.persistent));
+            }
+        }
+        changes
+    }
+
+    /// d20 plus the initiative modifier
+    pub fn roll_initiative(&self, rng: &mut impl Rng) -> Roll {
+        Expression::parse(&format!("1d20{:+}", self.initiative_modifier))
+            .expect("Initiative expression is always valid")
+            .roll(rng)
+    }
+
+    pub fn health_text(&self) -> String {
+        let mut text = if self.max_health > 0 {
+            format!("{}/{}", self.health, self.max_health)
+        } else {
+            self.health.to_string()
+        };
+        if self.temp_health > 0 {
+            text = format!("{} (+{})", text, self.temp_health);
+        }
+        text
+    }
+
+    /// What the players can tell from looking at it, instead of the numbers
+    pub fn health_description(&self) -> &'static str {
+        if self.dead || self.health <= 0 {
+            "down"
+        } else if self.max_health <= 0 || self.health >= self.max_health {
+            "unhurt"
+        } else if self.health * 2 > self.max_health {
+            "hurt"
+        } else if self.health * 4 > self.max_health {
+            "bloodied"
+        } else {
+            "near death"
+        }
+    }
+}
+
+/// What a creature took from some damage
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DamageTaken {
+    pub amount: u32,
+    // Immunities, weaknesses and resistances that applied, and persistent damage that started
+    pub applied: Vec<String>,
+    // Going down or dying further, see Creature::apply_health_shift
+    pub health_change: Option<String>,
+}
+
+/// Persistent damage rolled at the end of a turn, with the flat check to end it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PersistentTick {
+    pub persistent: PersistentDamage,
+    pub damage: Damage,
+    pub check: Check,
+    // As the check says, or as the player says their own roll went
+    pub ends: bool,
+}
+
+/// Saving throw modifiers
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Saves {
+    pub fortitude: i32,
+    pub reflex: i32,
+    pub will: i32,
+}
+
+impl Saves {
+    pub fn get(&self, kind: SaveKind) -> i32 {
+        match kind {
+            SaveKind::Fortitude => self.fortitude,
+            SaveKind::Reflex => self.reflex,
+            SaveKind::Will => self.will,
+        }
+    }
+}
+
+impl Display for Saves {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Fort {:+}, Ref {:+}, Will {:+}",
+            self.fortitude, self.reflex, self.will
+        )
+    }
+}
+
+impl Default for Creature {
+    fn default() -> Self {
+        Creature {
+            name: "".into(),
+            health: 0,
+            max_health: 0,
+            health_dice: None,
+            temp_health: 0,
+            armor_class: 0,
+            saves: Saves::default(),
+            perception: 0,
+            adjustments: Adjustments::default(),
+            initiative: None,
+            initiative_modifier: 0,
+            side: Side::default(),
+            conditions: vec![],
+            persistent: vec![],
+            dead: false,
+            notes: "".into(),
+            notes_cursor_pos: (0, 0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::damage::DamageExpression;
+
+    fn creature(name: &str, initiative: Option<i32>, modifier: i32, side: Side) -> Creature {
+        Creature {
+            initiative,
+            initiative_modifier: modifier,
+            side,
+            ..Creature::new(name)
+        }
+    }
+
+    fn names(encounter: &Encounter, order: &[usize]) -> Vec<String> {
+        order
+            .iter()
+            .map(|index| encounter.creatures[*index].name.clone())
+            .collect()
+    }
+
+    #[test]
+    fn deleting_the_active_creature_passes_the_turn_in_initiative_order() {
+        let mut encounter = Encounter::new(vec![
+            creature("A", Some(10), 0, Side::Enemy),
+            creature("B", Some(20), 0, Side::Enemy),
+            creature("C", Some(5), 0, Side::Enemy),
+        ]);
+        encounter.next_turn();
+        assert_eq!(encounter.active_creature, Some(1));
+        encounter.delete(1);
+        assert_eq!(encounter.active_creature, Some(0));
+
+        // The last in the order hands over to the first, in a new round
+        encounter.next_turn();
+        assert_eq!(encounter.active_creature, Some(1));
+        encounter.delete(1);
+        assert_eq!(encounter.active_creature, Some(0));
+        assert_eq!(encounter.round, 2);
+
+        encounter.delete(0);
+        assert_eq!(encounter.active_creature, None);
+        assert_eq!(encounter.round, 0);
+    }
+
+    #[test]
+    fn ties_are_broken_by_the_chosen_rule() {
+        let mut encounter = Encounter::new(vec![
+            creature("Party", Some(10), 5, Side::Party),
+            creature("Ally", Some(10), 3, Side::Ally),
+            creature("Enemy", Some(10), 1, Side::Enemy),
+            creature("Neutral", Some(10), 0, Side::Neutral),
+            creature("Fast", Some(15), 0, Side::Party),
+        ]);
+        let order = |encounter: &Encounter| names(encounter, &encounter.initiative_order());
+
+        encounter.tie_break = TieBreak::Side;
+        assert_eq!(
+            order(&encounter),
+            ["Fast", "Enemy", "Neutral", "Ally", "Party"]
+        );
+        encounter.tie_break = TieBreak::Modifier;
+        assert_eq!(
+            order(&encounter),
+            ["Fast", "Party", "Ally", "Enemy", "Neutral"]
+        );
+        encounter.tie_break = TieBreak::Manual;
+        assert_eq!(
+            order(&encounter),
+            ["Fast", "Party", "Ally", "Enemy", "Neutral"]
+        );
+        encounter.swap(0, 1);
+        assert_eq!(
+            order(&encounter),
+            ["Fast", "Ally", "Party", "Enemy", "Neutral"]
+        );
+    }
+
+    #[test]
+    fn creatures_without_initiative_go_last() {
+        let encounter = Encounter::new(vec![
+            creature("Unrolled", None, 10, Side::Enemy),
+            creature("Slow", Some(-2), 0, Side::Party),
+            creature("Also unrolled", None, 0, Side::Enemy),
+        ]);
+        assert_eq!(
+            names(&encounter, &encounter.initiative_order()),
+            ["Slow", "Unrolled", "Also unrolled"]
+        );
+    }
+
+    #[test]
+    fn turns_go_round_in_initiative_order() {
+        let mut encounter = Encounter::new(vec![
+            creature("Slow", Some(5), 0, Side::Enemy),
+            creature("Fast", Some(20), 0, Side::Party),
+        ]);
+        conditions::set(
+            &mut encounter.creatures[1].conditions,
+            ConditionKind::Frightened,
+            2,
+        );
+
+        assert_eq!(encounter.next_turn(), Vec::<String>::new());
+        assert_eq!((encounter.active_creature, encounter.round), (Some(1), 1));
+        // Conditions tick down when the turn ends
+        assert_eq!(
+            encounter.next_turn(),
+            ["Fast: Frightened reduced to 1".to_string()]
+        );
+        assert_eq!((encounter.active_creature, encounter.round), (Some(0), 1));
+        encounter.next_turn();
+        assert_eq!((encounter.active_creature, encounter.round), (Some(1), 2));
+
+        encounter.previous_turn();
+        assert_eq!((encounter.active_creature, encounter.round), (Some(0), 1));
+        encounter.previous_turn();
+        assert_eq!((encounter.active_creature, encounter.round), (Some(1), 1));
+        encounter.previous_turn();
+        assert_eq!((encounter.active_creature, encounter.round), (None, 0));
+    }
+
+    #[test]
+    fn deleting_and_swapping_keep_the_turn_with_the_same_creature() {
+        let mut encounter = Encounter::new(vec![
+            creature("A", None, 0, Side::Enemy),
+            creature("B", None, 0, Side::Enemy),
+            creature("C", None, 0, Side::Enemy),
+        ]);
+        encounter.active_creature = Some(2);
+        assert_eq!(encounter.delete(0).unwrap().name, "A");
+        assert_eq!(encounter.active_creature, Some(1));
+        assert!(encounter.delete(5).is_none());
+
+        encounter.swap(0, 1);
+        assert_eq!(names(&encounter, &[0, 1]), ["C", "B"]);
+        assert_eq!(encounter.active_creature, Some(0));
+    }
+
+    #[test]
+    fn copies_are_numbered_after_the_highest_so_far() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut encounter = Encounter::new(vec![
+            creature("Goblin", None, 0, Side::Enemy),
+            creature("Goblin 3", None, 0, Side::Enemy),
+            creature("Orc", None, 0, Side::Enemy),
+        ]);
+        encounter.active_creature = Some(2);
+
+        assert_eq!(encounter.duplicate(0, 2, &mut rng), Some(2..4));
+        assert_eq!(
+            names(&encounter, &[0, 1, 2, 3, 4]),
+            ["Goblin", "Goblin 3", "Goblin 4", "Goblin 5", "Orc"]
+        );
+        assert_eq!(encounter.active_creature, Some(4));
+
+        encounter.copies.numbering = Numbering::Letters;
+        encounter.duplicate(4, 2, &mut rng);
+        assert_eq!(names(&encounter, &[5, 6]), ["Orc B", "Orc C"]);
+        assert_eq!(encounter.duplicate(9, 1, &mut rng), None);
+    }
+
+    #[test]
+    fn copies_past_the_last_tag_are_refused() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut encounter =
+            Encounter::new(vec![creature("Goblin 4294967294", None, 0, Side::Enemy)]);
+        assert_eq!(encounter.duplicate(0, 2, &mut rng), None);
+        assert_eq!(encounter.duplicate(0, usize::MAX, &mut rng), None);
+        assert_eq!(encounter.duplicate(0, 1, &mut rng), Some(1..2));
+        assert_eq!(names(&encounter, &[1]), ["Goblin 4294967295"]);
+        assert_eq!(encounter.duplicate(0, 1, &mut rng), None);
+        assert_eq!(encounter.creatures.len(), 2);
+
+        // Letters stop at ZZ, three letters would read as part of the name
+        encounter.copies.numbering = Numbering::Letters;
+        encounter.creatures = vec![creature("Orc ZY", None, 0, Side::Enemy)];
+        assert_eq!(encounter.duplicate(0, 2, &mut rng), None);
+        encounter.duplicate(0, 1, &mut rng);
+        assert_eq!(names(&encounter, &[1]), ["Orc ZZ"]);
+        assert_eq!(
+            Numbering::Letters.split("Orc ZZ"),
+            ("Orc", Numbering::Letters.last())
+        );
+    }
+
+    #[test]
+    fn huge_hits_never_heal() {
+        let mut goblin = Creature {
+            health: 5,
+            adjustments: "weak fire 3000000000".parse().unwrap(),
+            ..Creature::new("Goblin")
+        };
+        let (damage, _) = DamageExpression::parse("1 fire")
+            .unwrap()
+            .roll(&mut StdRng::seed_from_u64(0));
+        let taken = goblin.take_damage(&damage);
+        assert_eq!(taken.amount, 3_000_000_001);
+        assert_eq!(goblin.health, 5 - i32::MAX);
+        assert!(goblin.dead);
+
+        goblin.apply_health_shift(HealthShift::Decrease(u32::MAX));
+        assert_eq!(goblin.health, i32::MIN);
+    }
+
+    #[test]
+    fn enemies_are_defeated_at_zero() {
+        let mut goblin = Creature {
+            health: 5,
+            ..Creature::new("Goblin")
+        };
+        assert_eq!(goblin.apply_health_shift(HealthShift::Decrease(3)), None);
+        assert_eq!(
+            goblin
+                .apply_health_shift(HealthShift::Decrease(4))
+                .as_deref(),
+            Some("was defeated")
+        );
+        assert!(goblin.dead);
+        assert_eq!(goblin.health, -2);
+        assert_eq!(
+            goblin.set_health(1).as_deref(),
+            Some("is back in the fight")
+        );
+        assert!(!goblin.dead);
+    }
+
+    #[test]
+    fn enemies_hit_at_zero_are_defeated() {
+        // No health set yet
+        let mut goblin = Creature::new("Goblin");
+        assert_eq!(goblin.set_health(0), None);
+        assert_eq!(goblin.apply_health_shift(HealthShift::Decrease(0)), None);
+        assert!(!goblin.dead);
+        assert_eq!(
+            goblin
+                .apply_health_shift(HealthShift::Decrease(3))
+                .as_deref(),
+            Some("was defeated")
+        );
+        assert!(goblin.dead);
+
+        // Unless temporary hit points soak all of it
+        let mut ally = Creature {
+            temp_health: 5,
+            side: Side::Ally,
+            ..Creature::new("Guard")
+        };
+        assert_eq!(ally.apply_health_shift(HealthShift::Decrease(3)), None);
+        assert!(!ally.dead);
+    }
+
+    #[test]
+    fn characters_go_through_dying_and_wounded() {
+        let mut hero = Creature {
+            health: 5,
+            side: Side::Party,
+            ..Creature::new("Hero")
+        };
+        assert_eq!(
+            hero.apply_health_shift(HealthShift::Decrease(9)).as_deref(),
+            Some("is dying 1")
+        );
+        assert_eq!(hero.health, 0);
+        assert_eq!(hero.recovery_dc(), 11);
+        assert_eq!(
+            hero.recover(Degree::Success),
+            "is no longer dying, wounded 1"
+        );
+        assert!(!hero.is_dying());
+        assert_eq!(
+            hero.apply_health_shift(HealthShift::Increase(2)).as_deref(),
+            Some("woke up")
+        );
+
+        // Wounded makes the next fall worse, doomed brings death closer
+        conditions::set(&mut hero.conditions, ConditionKind::Doomed, 1);
+        assert_eq!(hero.set_health(0).as_deref(), Some("is dying 2"));
+        assert_eq!(hero.recover(Degree::CriticalFailure), "died");
+        assert!(hero.dead);
+        assert!(!hero.is_dying());
+    }
+
+    #[test]
+    fn characters_hit_while_down_start_dying_again() {
+        let mut hero = Creature {
+            health: 1,
+            side: Side::Party,
+            ..Creature::new("Hero")
+        };
+        hero.apply_health_shift(HealthShift::Decrease(1));
+        assert_eq!(
+            hero.recover(Degree::CriticalSuccess),
+            "is no longer dying, wounded 1"
+        );
+        assert_eq!(hero.health, 0);
+
+        assert_eq!(
+            hero.apply_health_shift(HealthShift::Decrease(3)).as_deref(),
+            Some("is dying 2")
+        );
+        assert_eq!(
+            hero.apply_health_shift(HealthShift::Decrease(3)).as_deref(),
+            Some("is dying 3")
+        );
+        // Temporary hit points take the hit, nothing changes
+        hero.temp_health = 5;
+        assert_eq!(hero.apply_health_shift(HealthShift::Decrease(3)), None);
+    }
+
+    #[test]
+    fn healing_stops_at_the_maximum_without_lowering_health() {
+        let mut creature = Creature {
+            health: 3,
+            max_health: 10,
+            ..Default::default()
+        };
+        creature.apply_health_shift(HealthShift::Increase(20));
+        assert_eq!(creature.health, 10);
+
+        creature.health = 15;
+        creature.apply_health_shift(HealthShift::Increase(5));
+        assert_eq!(creature.health, 15);
+
+        creature.max_health = 0;
+        creature.apply_health_shift(HealthShift::Increase(5));
+        assert_eq!(creature.health, 20);
+
+        // Huge heals saturate instead of wrapping around
+        creature.apply_health_shift(HealthShift::Increase(u32::MAX));
+        assert_eq!(creature.health, i32::MAX);
+        creature.max_health = 10;
+        creature.health = 3;
+        creature.apply_health_shift(HealthShift::Increase(u32::MAX));
+        assert_eq!(creature.health, 10);
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::VecDeque;
+
+pub const DEFAULT_DEPTH: usize = 100;
+
+// Snapshot based undo/redo. Instead of every action describing how to reverse itself,
+// the app reports its state after each action and the history figures out what changed.
+#[derive(Debug, Clone)]
+pub struct History<T> {
+    undo: VecDeque<T>,
+    redo: Vec<T>,
+    // State after the last recorded change, what an undo returns to after a redo
+    committed: T,
+    depth: usize,
+}
+
+impl<T: Clone + PartialEq> History<T> {
+    pub fn new(initial: T, depth: usize) -> Self {
+        History {
+            undo: VecDeque::new(),
+            redo: vec![],
+            committed: initial,
+            depth,
+        }
+    }
+
+    pub fn set_depth(&mut self, depth: usize) {
+        self.depth = depth;
+        self.trim();
+    }
+
+    /// Forgets everything, used when the state is replaced wholesale
+    pub fn reset(&mut self, state: T) {
+        self.undo.clear();
+        self.redo.clear();
+        self.committed = state;
+    }
+
+    /// Records the current state as a new step if it differs from the last one
+    pub fn record(&mut self, current: &T) {
+        if *current == self.committed {
+            return;
+        }
+
+        let previous = std::mem::replace(&mut self.committed, current.clone());
+        self.undo.push_back(previous);
+        self.redo.clear();
+        self.trim();
+    }
+
+    pub fn undo(&mut self) -> Option<T> {
+        let previous = self.undo.pop_back()?;
+        let current = std::mem::replace(&mut self.committed, previous.clone());
+        self.redo.push(current);
+        Some(previous)
+    }
+
+    pub fn redo(&mut self) -> Option<T> {
+        let next = self.redo.pop()?;
+        let current = std::mem::replace(&mut self.committed, next.clone());
+        self.undo.push_back(current);
+        self.trim();
+        Some(next)
+    }
+
+    fn trim(&mut self) {
+        while self.undo.len() > self.depth {
+            self.undo.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unchanged_states_are_not_recorded() {
+        let mut history = History::new(1, DEFAULT_DEPTH);
+        history.record(&1);
+        assert_eq!(history.undo(), None);
+
+        history.record(&2);
+        history.record(&2);
+        assert_eq!(history.undo(), Some(1));
+        assert_eq!(history.undo(), None);
+    }
+
+    #[test]
+    fn undo_and_redo_round_trip() {
+        let mut history = History::new(1, DEFAULT_DEPTH);
+        history.record(&2);
+        history.record(&3);
+
+        assert_eq!(history.undo(), Some(2));
+        assert_eq!(history.undo(), Some(1));
+        assert_eq!(history.redo(), Some(2));
+        assert_eq!(history.redo(), Some(3));
+        assert_eq!(history.redo(), None);
+        assert_eq!(history.undo(), Some(2));
+    }
+
+    #[test]
+    fn new_changes_clear_the_redo_steps() {
+        let mut history = History::new(1, DEFAULT_DEPTH);
+        history.record(&2);
+        assert_eq!(history.undo(), Some(1));
+
+        history.record(&4);
+        assert_eq!(history.redo(), None);
+        assert_eq!(history.undo(), Some(1));
+    }
+
+    #[test]
+    fn only_the_last_steps_are_kept() {
+        let mut history = History::new(0, 5);
+        for state in 1..=4 {
+            history.record(&state);
+        }
+        // Lowering the depth drops the oldest steps
+        history.set_depth(2);
+        assert_eq!(history.undo(), Some(3));
+        assert_eq!(history.undo(), Some(2));
+        assert_eq!(history.undo(), None);
+
+        // Redoing doesn't grow past the depth either
+        history.redo();
+        history.redo();
+        history.record(&5);
+        assert_eq!(history.undo(), Some(4));
+        assert_eq!(history.undo(), Some(3));
+        assert_eq!(history.undo(), None);
+    }
+}
@@ -0,0 +1,390 @@
+use std::{fs, io, path::Path};
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{
+    damage::{Adjustments, Against},
+    encounter::{Creature, Saves, Side},
+};
+
+/// Reads a Pathbuilder 2e character or a Foundry VTT pf2e NPC export
+pub fn from_file(path: &Path) -> io::Result<Creature> {
+    let text = fs::read_to_string(path)?;
+    from_json(&text).map_err(|err| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {}", path.display(), err),
+        )
+    })
+}
+
+pub fn from_json(text: &str) -> Result<Creature, String> {
+    let value: Value = serde_json::from_str(text).map_err(|err| err.to_string())?;
+    if value.get("build").is_some() {
+        let export: PathbuilderExport =
+            serde_json::from_value(value).map_err(|err| err.to_string())?;
+        Ok(export.build.to_creature())
+    } else if value.get("system").is_some() {
+        let actor: FoundryActor = serde_json::from_value(value).map_err(|err| err.to_string())?;
+        actor.to_creature()
+    } else {
+        Err("not a Pathbuilder or Foundry pf2e export".into())
+    }
+}
+
+// Pathbuilder's "Export JSON", only the parts needed to work out the numbers
+#[derive(Deserialize)]
+struct PathbuilderExport {
+    build: PathbuilderBuild,
+}
+
+#[derive(Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct PathbuilderBuild {
+    name: String,
+    class: String,
+    ancestry: String,
+    level: i32,
+    abilities: Abilities,
+    attributes: PathbuilderAttributes,
+    // 0, 2, 4, 6 and 8 for untrained up to legendary
+    proficiencies: Proficiencies,
+    ac_total: ArmorClass,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct Abilities {
+    dex: i32,
+    con: i32,
+    wis: i32,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct PathbuilderAttributes {
+    ancestryhp: i32,
+    classhp: i32,
+    bonushp: i32,
+    #[serde(rename = "bonushpPerLevel")]
+    bonushp_per_level: i32,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct Proficiencies {
+    perception: i32,
+    fortitude: i32,
+    reflex: i32,
+    will: i32,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct ArmorClass {
+    ac_total: i32,
+}
+
+impl Default for PathbuilderBuild {
+    fn default() -> Self {
+        PathbuilderBuild {
+            name: String::new(),
+            class: String::new(),
+            ancestry: String::new(),
+            // Pathbuilder always writes it, but a level 0 character would add no hit points
+            level: 1,
+            abilities: Abilities::default(),
+            attributes: PathbuilderAttributes::default(),
+            proficiencies: Proficiencies::default(),
+            ac_total: ArmorClass::default(),
+        }
+    }
+}
+
+impl PathbuilderBuild {
+    fn to_creature(&self) -> Creature {
+        let modifier = |score: i32| (score - 10).div_euclid(2);
+        // Trained or better adds the level on top of the proficiency bonus
+        let proficiency = |bonus: i32| if bonus > 0 { bonus + self.level } else { 0 };
+
+        let attributes = &self.attributes;
+        let max_health = attributes.ancestryhp
+            + attributes.bonushp
+            + (attributes.classhp + attributes.bonushp_per_level + modifier(self.abilities.con))
+                * self.level;
+        let perception = modifier(self.abilities.wis) + proficiency(self.proficiencies.perception);
+
+        Creature {
+            name: self.name.clone(),
+            health: max_health,
+            max_health,
+            armor_class: self.ac_total.ac_total,
+            saves: Saves {
+                fortitude: modifier(self.abilities.con) + proficiency(self.proficiencies.fortitude),
+                reflex: modifier(self.abilities.dex) + proficiency(self.proficiencies.reflex),
+                will: modifier(self.abilities.wis) + proficiency(self.proficiencies.will),
+            },
+            perception,
+            initiative_modifier: perception,
+            side: Side::Party,
+            notes: format!("Level {} {} {}", self.level, self.ancestry, self.class),
+            ..Default::default()
+        }
+    }
+}
+
+// A Foundry pf2e actor as exported from the sidebar or a compendium
+#[derive(Deserialize)]
+struct FoundryActor {
+    name: String,
+    #[serde(rename = "type")]
+    kind: String,
+    system: FoundrySystem,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct FoundrySystem {
+    attributes: FoundryAttributes,
+    // Newer versions of the system moved perception out of the attributes
+    perception: Option<FoundryModifier>,
+    saves: FoundrySaves,
+    details: FoundryDetails,
+    traits: FoundryTraits,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct FoundryAttributes {
+    ac: FoundryValue,
+    hp: FoundryHealth,
+    perception: Option<FoundryValue>,
+    immunities: Vec<FoundryAdjustment>,
+    weaknesses: Vec<FoundryAdjustment>,
+    resistances: Vec<FoundryAdjustment>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct FoundryValue {
+    value: i32,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct FoundryModifier {
+    #[serde(rename = "mod")]
+    modifier: i32,
+}
+
+// Exceptions like "except adamantine" are left off, the tracker can't tell materials apart
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct FoundryAdjustment {
+    #[serde(rename = "type")]
+    kind: String,
+    value: u32,
+}
+
+impl FoundryAdjustment {
+    /// Foundry writes types like `cold-iron` and `all-damage`
+    fn against(&self) -> Against {
+        Against::parse(&self.kind.replace('-', " "))
+    }
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct FoundryHealth {
+    max: i32,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct FoundrySaves {
+    fortitude: FoundryValue,
+    reflex: FoundryValue,
+    will: FoundryValue,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct FoundryDetails {
+    level: FoundryValue,
+    public_notes: String,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct FoundryTraits {
+    value: Vec<String>,
+}
+
+impl FoundryActor {
+    fn to_creature(&self) -> Result<Creature, String> {
+        // Character sheets only store choices, the numbers are worked out when Foundry runs
+        if self.kind != "npc" {
+            return Err(format!(
+                "'{}' is a Foundry {}, only NPCs can be imported. Use Pathbuilder for characters.",
+                self.name, self.kind
+            ));
+        }
+
+        let system = &self.system;
+        let attributes = &system.attributes;
+        let perception = match (&system.perception, &attributes.perception) {
+            (Some(perception), _) => perception.modifier,
+            (None, Some(perception)) => perception.value,
+            (None, None) => 0,
+        };
+
+        let mut notes = format!("Level {}", system.details.level.value);
+        if !system.traits.value.is_empty() {
+            notes = format!("{}, {}", notes, system.traits.value.join(", "));
+        }
+        let description = strip_html(&system.details.public_notes);
+        if !description.is_empty() {
+            notes = format!("{}\n{}", notes, description);
+        }
+
+        Ok(Creature {
+            name: self.name.clone(),
+            health: attributes.hp.max,
+            max_health: attributes.hp.max,
+            armor_class: attributes.ac.value,
+            saves: Saves {
+                fortitude: system.saves.fortitude.value,
+                reflex: system.saves.reflex.value,
+                will: system.saves.will.value,
+            },
+            perception,
+            adjustments: Adjustments {
+                immunities: attributes
+                    .immunities
+                    .iter()
+                    .map(FoundryAdjustment::against)
+                    .collect(),
+                weaknesses: attributes
+                    .weaknesses
+                    .iter()
+                    .map(|weakness| (weakness.against(), weakness.value))
+                    .collect(),
+                resistances: attributes
+                    .resistances
+                    .iter()
+                    .map(|resistance| (resistance.against(), resistance.value))
+                    .collect(),
+            },
+            initiative_modifier: perception,
+            notes,
+            ..Default::default()
+        })
+    }
+}
+
+/// Foundry keeps descriptions as HTML, the notes pane wants plain lines
+fn strip_html(html: &str) -> String {
+    let mut text = String::new();
+    let mut tag = None;
+    for ch in html.chars() {
+        match (ch, &mut tag) {
+            ('<', None) => tag = Some(String::new()),
+            ('>', Some(name)) => {
+                let name = name.trim_start_matches('/').to_lowercase();
+                if name.starts_with('p') || name.starts_with("br") || name.starts_with("li") {
+                    text.push('\n');
+                }
+                tag = None;
+            }
+            (ch, Some(name)) => name.push(ch),
+            (ch, None) => text.push(ch),
+        }
+    }
+
+    text.replace("&amp;", "&")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&nbsp;", " ")
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pathbuilder_characters_work_out_their_numbers() {
+        let creature = from_json(
+            r#"{"success": true, "build": {
+                "name": "Amiri", "class": "Barbarian", "ancestry": "Human", "level": 3,
+                "abilities": {"str": 18, "dex": 14, "con": 16, "int": 10, "wis": 12, "cha": 8},
+                "attributes": {"ancestryhp": 8, "classhp": 12, "bonushp": 0, "bonushpPerLevel": 0},
+                "proficiencies": {"perception": 4, "fortitude": 4, "reflex": 2, "will": 4},
+                "acTotal": {"acTotal": 19}
+            }}"#,
+        )
+        .unwrap();
+
+        assert_eq!(creature.name, "Amiri");
+        assert_eq!((creature.health, creature.max_health), (53, 53));
+        assert_eq!(creature.armor_class, 19);
+        assert_eq!(
+            creature.saves,
+            Saves {
+                fortitude: 10,
+                reflex: 7,
+                will: 8
+            }
+        );
+        assert_eq!(creature.perception, 8);
+        assert_eq!(creature.side, Side::Party);
+        assert_eq!(creature.notes, "Level 3 Human Barbarian");
+    }
+
+    #[test]
+    fn foundry_npcs_are_read_from_either_perception_layout() {
+        let creature = from_json(
+            r#"{"name": "Goblin Warrior", "type": "npc", "system": {
+                "attributes": {"ac": {"value": 16}, "hp": {"value": 6, "max": 6},
+                    "immunities": [{"type": "sleep"}],
+                    "weaknesses": [{"type": "cold-iron", "value": 3}, {"type": "fire", "value": 2}],
+                    "resistances": [{"type": "all-damage", "value": 1, "exceptions": ["fire"]}]},
+                "perception": {"mod": 2},
+                "saves": {"fortitude": {"value": 5}, "reflex": {"value": 7}, "will": {"value": 3}},
+                "details": {"level": {"value": -1}, "publicNotes": "<p>Sneaky &amp; mean.</p><p>Hates dogs.</p>"},
+                "traits": {"value": ["goblin", "humanoid"]}
+            }}"#,
+        )
+        .unwrap();
+
+        assert_eq!((creature.health, creature.armor_class), (6, 16));
+        assert_eq!(creature.saves.reflex, 7);
+        assert_eq!(creature.initiative_modifier, 2);
+        assert_eq!(
+            creature.adjustments.to_string(),
+            "immune sleep; weak cold iron 3, fire 2; resist all 1"
+        );
+        assert_eq!(
+            creature.notes,
+            "Level -1, goblin, humanoid\nSneaky & mean.\nHates dogs."
+        );
+
+        let legacy = from_json(
+            r#"{"name": "Kobold", "type": "npc", "system": {"attributes": {"perception": {"value": 3}}}}"#,
+        )
+        .unwrap();
+        assert_eq!(legacy.perception, 3);
+    }
+
+    #[test]
+    fn other_json_is_rejected() {
+        assert!(from_json(r#"{"name": "Ezren", "type": "character", "system": {}}"#).is_err());
+        assert!(from_json(r#"{"creatures": []}"#).is_err());
+        assert!(from_json("not json").is_err());
+    }
+}
@@ -0,0 +1,431 @@
+use std::{
+    collections::HashMap,
+    io::{self},
+};
+
+use ratatui::crossterm::event::KeyCode;
+use serde::Deserialize;
+
+/// Where an action's keys work. A key can do different things in different contexts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Context {
+    // Also the navigation in the other modes
+    Normal,
+    Sort,
+    Conditions,
+    // Picking the save and typing the DC of a basic save
+    SaveDc,
+    // Saves, recovery checks and flat checks
+    Outcome,
+    // Crash recovery prompt
+    Recover,
+}
+
+/// Everything normal mode can do, then what the other modes do besides navigating
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Menu,
+    Help,
+    Sort,
+    SelectFirst,
+    SelectPrevious,
+    SelectNext,
+    SelectLast,
+    MoveUp,
+    MoveDown,
+    NextTurn,
+    PreviousTurn,
+    Undo,
+    Redo,
+    Add,
+    Rename,
+    Duplicate,
+    DuplicateMany,
+    Delete,
+    EditNotes,
+    EditConditions,
+    SetHealth,
+    SetMaxHealth,
+    SetTempHealth,
+    SetInitiative,
+    SetInitiativeModifier,
+    RollInitiative,
+    ToggleSide,
+    FilterSide,
+    PlayerView,
+    Damage,
+    Heal,
+    BasicSave,
+    SetDefenses,
+    SetAdjustments,
+    AddFromBestiary,
+    SaveTemplate,
+    ToggleMark,
+    ClearMarks,
+    // Sort mode, the reversed ones flip the direction
+    SortByInitiative,
+    SortByInitiativeReversed,
+    SortByHealth,
+    SortByHealthReversed,
+    SortByName,
+    SortByNameReversed,
+    // Condition mode
+    IncreaseCondition,
+    DecreaseCondition,
+    CycleConditionRule,
+    // Basic save DC prompt
+    Fortitude,
+    Reflex,
+    Will,
+    NextSave,
+    // Outcome pickers
+    CriticalFailure,
+    Failure,
+    Success,
+    CriticalSuccess,
+    WorseOutcome,
+    BetterOutcome,
+    // Crash recovery prompt
+    RestoreSession,
+    DiscardSession,
+}
+
+impl Action {
+    pub const ALL: [Action; 59] = [
+        Action::Menu,
+        Action::Help,
+        Action::Sort,
+        Action::SelectFirst,
+        Action::SelectPrevious,
+        Action::SelectNext,
+        Action::SelectLast,
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::NextTurn,
+        Action::PreviousTurn,
+        Action::Undo,
+        Action::Redo,
+        Action::Add,
+        Action::Rename,
+        Action::Duplicate,
+        Action::DuplicateMany,
+        Action::Delete,
+        Action::EditNotes,
+        Action::EditConditions,
+        Action::SetHealth,
+        Action::SetMaxHealth,
+        Action::SetTempHealth,
+        Action::SetInitiative,
+        Action::SetInitiativeModifier,
+        Action::RollInitiative,
+        Action::ToggleSide,
+        Action::FilterSide,
+        Action::PlayerView,
+        Action::Damage,
+        Action::Heal,
+        Action::BasicSave,
+        Action::SetDefenses,
+        Action::SetAdjustments,
+        Action::AddFromBestiary,
+        Action::SaveTemplate,
+        Action::ToggleMark,
+        Action::ClearMarks,
+        Action::SortByInitiative,
+        Action::SortByInitiativeReversed,
+        Action::SortByHealth,
+        Action::SortByHealthReversed,
+        Action::SortByName,
+        Action::SortByNameReversed,
+        Action::IncreaseCondition,
+        Action::DecreaseCondition,
+        Action::CycleConditionRule,
+        Action::Fortitude,
+        Action::Reflex,
+        Action::Will,
+        Action::NextSave,
+        Action::CriticalFailure,
+        Action::Failure,
+        Action::Success,
+        Action::CriticalSuccess,
+        Action::WorseOutcome,
+        Action::BetterOutcome,
+        Action::RestoreSession,
+        Action::DiscardSession,
+    ];
+
+    pub fn context(&self) -> Context {
+        match self {
+            Action::SortByInitiative
+            | Action::SortByInitiativeReversed
+            | Action::SortByHealth
+            | Action::SortByHealthReversed
+            | Action::SortByName
+            | Action::SortByNameReversed => Context::Sort,
+            Action::IncreaseCondition | Action::DecreaseCondition | Action::CycleConditionRule => {
+                Context::Conditions
+            }
+            Action::Fortitude | Action::Reflex | Action::Will | Action::NextSave => Context::SaveDc,
+            Action::CriticalFailure
+            | Action::Failure
+            | Action::Success
+            | Action::CriticalSuccess
+            | Action::WorseOutcome
+            | Action::BetterOutcome => Context::Outcome,
+            Action::RestoreSession | Action::DiscardSession => Context::Recover,
+            _ => Context::Normal,
+        }
+    }
+
+    // Today's bindings, a config file can replace them one action at a time
+    fn default_keys(&self) -> &'static [KeyCode] {
+        match self {
+            Action::Menu => &[KeyCode::Esc],
+            Action::Help => &[KeyCode::Char('?')],
+            Action::Sort => &[KeyCode::Char('s')],
+            Action::SelectFirst => &[KeyCode::Char('K')],
+            Action::SelectPrevious => &[KeyCode::Char('k')],
+            Action::SelectNext => &[KeyCode::Char('j')],
+            Action::SelectLast => &[KeyCode::Char('J')],
+            Action::MoveUp => &[KeyCode::Char('<')],
+            Action::MoveDown => &[KeyCode::Char('>')],
+            Action::NextTurn => &[KeyCode::Char('t')],
+            Action::PreviousTurn => &[KeyCode::Char('T')],
+            Action::Undo => &[KeyCode::Char('u')],
+            Action::Redo => &[KeyCode::Char('U')],
+            Action::Add => &[KeyCode::Char('a')],
+            Action::Rename => &[KeyCode::Char('r')],
+            Action::Duplicate => &[KeyCode::Char('c')],
+            Action::DuplicateMany => &[KeyCode::Char('C')],
+            Action::Delete => &[KeyCode::Char('d')],
+            Action::EditNotes => &[KeyCode::Char('n')],
+            Action::EditConditions => &[KeyCode::Char('e')],
+            Action::SetHealth => &[KeyCode::Char('h')],
+            Action::SetMaxHealth => &[KeyCode::Char('m')],
+            Action::SetTempHealth => &[KeyCode::Char('p')],
+            Action::SetInitiative => &[KeyCode::Char('i')],
+            Action::SetInitiativeModifier => &[KeyCode::Char('b')],
+            Action::RollInitiative => &[KeyCode::Char('I')],
+            Action::ToggleSide => &[KeyCode::Char('f')],
+            Action::FilterSide => &[KeyCode::Char('F')],
+            Action::PlayerView => &[KeyCode::Char('P')],
+            Action::Damage => &[KeyCode::Char('-')],
+            Action::Heal => &[KeyCode::Char('+')],
+            Action::BasicSave => &[KeyCode::Char('B')],
+            Action::SetDefenses => &[KeyCode::Char('v')],
+            Action::SetAdjustments => &[KeyCode::Char('w')],
+            Action::AddFromBestiary => &[KeyCode::Char('A')],
+            Action::SaveTemplate => &[KeyCode::Char('S')],
+            Action::ToggleMark => &[KeyCode::Char(' ')],
+            Action::ClearMarks => &[KeyCode::Char('x')],
+            Action::SortByInitiative => &[KeyCode::Char('i')],
+            Action::SortByInitiativeReversed => &[KeyCode::Char('I')],
+            Action::SortByHealth => &[KeyCode::Char('h')],
+            Action::SortByHealthReversed => &[KeyCode::Char('H')],
+            Action::SortByName => &[KeyCode::Char('n')],
+            Action::SortByNameReversed => &[KeyCode::Char('N')],
+            Action::IncreaseCondition => &[KeyCode::Char('+'), KeyCode::Enter],
+            Action::DecreaseCondition => &[KeyCode::Char('-'), KeyCode::Backspace],
+            Action::CycleConditionRule => &[KeyCode::Char('r')],
+            Action::Fortitude => &[KeyCode::Char('f')],
+            Action::Reflex => &[KeyCode::Char('r')],
+            Action::Will => &[KeyCode::Char('w')],
+            Action::NextSave => &[KeyCode::Tab],
+            Action::CriticalFailure => &[KeyCode::Char('1')],
+            Action::Failure => &[KeyCode::Char('2')],
+            Action::Success => &[KeyCode::Char('3')],
+            Action::CriticalSuccess => &[KeyCode::Char('4')],
+            Action::WorseOutcome => &[KeyCode::Left],
+            Action::BetterOutcome => &[KeyCode::Right],
+            Action::RestoreSession => &[KeyCode::Char('y'), KeyCode::Enter],
+            Action::DiscardSession => &[KeyCode::Char('n'), KeyCode::Esc],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keymap {
+    bindings: HashMap<Action, Vec<KeyCode>>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Keymap {
+            bindings: Action::ALL
+                .iter()
+                .map(|action| (*action, action.default_keys().to_vec()))
+                .collect(),
+        }
+    }
+}
+
+impl Keymap {
+    /// Defaults with the given actions rebound, keys are written like `j`, `Down` or `Space`
+    pub fn with_overrides(overrides: &HashMap<Action, Vec<String>>) -> io::Result<Self> {
+        let mut keymap = Keymap::default();
+        for (action, keys) in overrides {
+            let keys = keys
+                .iter()
+                .map(|key| parse_key(key))
+                .collect::<io::Result<Vec<_>>>()?;
+            keymap.bind(*action, keys);
+        }
+        Ok(keymap)
+    }
+
+    /// Replaces the keys of the action, taking them away from any other action in its context
+    pub fn bind(&mut self, action: Action, keys: Vec<KeyCode>) {
+        for (other, bound) in self.bindings.iter_mut() {
+            if other.context() == action.context() {
+                bound.retain(|key| !keys.contains(key));
+            }
+        }
+        self.bindings.insert(action, keys);
+    }
+
+    /// Normal mode action of the key, also what navigates in the other modes
+    pub fn action(&self, key: KeyCode) -> Option<Action> {
+        self.action_in(Context::Normal, key)
+    }
+
+    pub fn action_in(&self, context: Context, key: KeyCode) -> Option<Action> {
+        // Go through ALL so the result doesn't depend on hash order
+        Action::ALL
+            .into_iter()
+            .filter(|action| action.context() == context)
+            .find(|action| self.keys(*action).contains(&key))
+    }
+
+    pub fn keys(&self, action: Action) -> &[KeyCode] {
+        self.bindings
+            .get(&action)
+            .map(|keys| keys.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Keys of the actions for showing to the user, like `k/j`
+    pub fn describe(&self, actions: &[Action]) -> String {
+        let keys: Vec<String> = actions
+            .iter()
+            .flat_map(|action| self.keys(*action))
+            .map(key_name)
+            .collect();
+        if keys.is_empty() {
+            "unbound".into()
+        } else {
+            keys.join("/")
+        }
+    }
+}
+
+fn parse_key(key: &str) -> io::Result<KeyCode> {
+    let mut chars = key.chars();
+    if let (Some(ch), None) = (chars.next(), chars.next()) {
+        return Ok(KeyCode::Char(ch));
+    }
+
+    Ok(match key.to_lowercase().as_str() {
+        "space" => KeyCode::Char(' '),
+        "esc" | "escape" => KeyCode::Esc,
+        "enter" | "return" => KeyCode::Enter,
+        "tab" => KeyCode::Tab,
+        "backspace" => KeyCode::Backspace,
+        "delete" | "del" => KeyCode::Delete,
+        "insert" | "ins" => KeyCode::Insert,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        function => match function
+            .strip_prefix('f')
+            .and_then(|number| number.parse().ok())
+        {
+            Some(number) if (1..=12).contains(&number) => KeyCode::F(number),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("'{}' is not a key this app knows", key),
+                ))
+            }
+        },
+    })
+}
+
+pub fn key_name(key: &KeyCode) -> String {
+    match key {
+        KeyCode::Char(' ') => "Space".into(),
+        KeyCode::Char(ch) => ch.to_string(),
+        KeyCode::F(number) => format!("F{}", number),
+        KeyCode::PageUp => "PageUp".into(),
+        KeyCode::PageDown => "PageDown".into(),
+        other => format!("{:?}", other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_parse_by_name_and_character() {
+        assert_eq!(parse_key("j").unwrap(), KeyCode::Char('j'));
+        assert_eq!(parse_key("J").unwrap(), KeyCode::Char('J'));
+        assert_eq!(parse_key("space").unwrap(), KeyCode::Char(' '));
+        assert_eq!(parse_key("PageDown").unwrap(), KeyCode::PageDown);
+        assert_eq!(parse_key("F5").unwrap(), KeyCode::F(5));
+        assert!(parse_key("F13").is_err());
+        assert!(parse_key("hyper").is_err());
+    }
+
+    #[test]
+    fn key_names_parse_back() {
+        for action in Action::ALL {
+            for key in action.default_keys() {
+                assert_eq!(parse_key(&key_name(key)).unwrap(), *key);
+            }
+        }
+    }
+
+    #[test]
+    fn rebinding_takes_the_key_from_other_actions() {
+        let overrides = HashMap::from([(Action::SelectNext, vec!["n".into(), "Down".into()])]);
+        let keymap = Keymap::with_overrides(&overrides).unwrap();
+
+        assert_eq!(keymap.action(KeyCode::Char('n')), Some(Action::SelectNext));
+        assert_eq!(keymap.action(KeyCode::Down), Some(Action::SelectNext));
+        assert_eq!(keymap.action(KeyCode::Char('j')), None);
+        assert_eq!(keymap.describe(&[Action::EditNotes]), "unbound");
+    }
+
+    #[test]
+    fn default_keys_do_one_thing_per_context() {
+        let keymap = Keymap::default();
+        for action in Action::ALL {
+            for key in action.default_keys() {
+                assert_eq!(keymap.action_in(action.context(), *key), Some(action));
+            }
+        }
+    }
+
+    #[test]
+    fn keys_are_shared_between_contexts() {
+        let overrides = HashMap::from([(Action::Reflex, vec!["x".into()])]);
+        let keymap = Keymap::with_overrides(&overrides).unwrap();
+
+        // Clear marks keeps x in normal mode, the condition rule keeps r
+        assert_eq!(keymap.action(KeyCode::Char('x')), Some(Action::ClearMarks));
+        assert_eq!(
+            keymap.action_in(Context::SaveDc, KeyCode::Char('x')),
+            Some(Action::Reflex)
+        );
+        assert_eq!(
+            keymap.action_in(Context::Conditions, KeyCode::Char('r')),
+            Some(Action::CycleConditionRule)
+        );
+        assert_eq!(keymap.action_in(Context::SaveDc, KeyCode::Char('r')), None);
+    }
+}
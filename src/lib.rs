@@ -0,0 +1,15 @@
+//! Encounter engine behind the combat tracker, usable without the terminal UI
+
+pub mod bestiary;
+pub mod checks;
+pub mod conditions;
+pub mod damage;
+pub mod dice;
+pub mod encounter;
+pub mod history;
+pub mod import;
+pub mod save;
+
+pub use encounter::{
+    CopyRules, Creature, Encounter, HealthShift, Numbering, Saves, Side, TieBreak,
+};
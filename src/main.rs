@@ -1,10 +1,20 @@
 use clap::Parser;
 use log::info;
+use ratatui::crossterm::{
+    event::{DisableMouseCapture, EnableMouseCapture},
+    execute,
+};
 use simplelog::{Config, LevelFilter, WriteLogger};
 use std::{fs::File, io};
 
+use combat_tracker::bestiary::Bestiary;
+
 mod app;
 mod args;
+mod autosave;
+mod config;
+mod keymap;
+mod theme;
 
 fn main() -> io::Result<()> {
     let parsed_args = args::Args::parse();
@@ -18,8 +28,50 @@ fn main() -> io::Result<()> {
         info!("Beginning of log");
     }
 
+    // Load before taking over the terminal so errors are readable
+    let config = match parsed_args.config.or_else(config::Config::default_path) {
+        Some(path) => config::Config::load(&path)?,
+        None => config::Config::default(),
+    };
+    let bestiary_path = parsed_args.bestiary.or_else(Bestiary::default_path);
+    let bestiary = match &bestiary_path {
+        Some(path) => Bestiary::load(path)?,
+        None => Bestiary::default(),
+    };
+    let mut app = match parsed_args.load {
+        Some(path) => app::App::load(&path)?,
+        None => app::App::new(parsed_args.init_test_creatures),
+    }
+    .with_history_depth(parsed_args.history_depth)
+    .with_keymap(config.keymap()?)
+    .with_bestiary(bestiary, bestiary_path);
+    // Before the imports, so they are added to a recovered session too
+    if !parsed_args.no_autosave {
+        if let Some(autosave) = autosave::Autosave::in_state_dir() {
+            app = app.with_autosave(autosave);
+        }
+    }
+    app = app
+        .with_imports(&parsed_args.import)?
+        // Command line beats config beats NO_COLOR
+        .with_theme(theme::Theme::named(
+            parsed_args
+                .theme
+                .or(config.theme)
+                .unwrap_or_else(theme::ThemeName::from_env),
+        ));
+    if let Some(seed) = parsed_args.seed {
+        app = app.with_seed(seed);
+    }
+
     let terminal = ratatui::init();
-    let result = app::App::new(parsed_args.init_test_creatures).run(terminal);
+    if !parsed_args.no_mouse {
+        execute!(io::stdout(), EnableMouseCapture)?;
+    }
+    let result = app.run(terminal);
+    if !parsed_args.no_mouse {
+        execute!(io::stdout(), DisableMouseCapture)?;
+    }
     ratatui::restore();
     result
 }
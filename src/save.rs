@@ -0,0 +1,117 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Write},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::encounter::Encounter;
+
+// Bump this when the format changes in a way older builds can't read
+pub const SAVE_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SaveFile {
+    pub version: u32,
+    pub selected_creature: Option<usize>,
+    // Flattened so the encounter fields sit at the top level like they always have
+    #[serde(flatten)]
+    pub encounter: Encounter,
+}
+
+impl SaveFile {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        let save: SaveFile = serde_json::from_reader(reader)?;
+
+        if save.version > SAVE_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "{} is save version {}, this build only understands up to {}",
+                    path.display(),
+                    save.version,
+                    SAVE_VERSION
+                ),
+            ));
+        }
+
+        Ok(save)
+    }
+
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::encounter::Creature;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "combat-tracker-save-{}-{}",
+            std::process::id(),
+            name
+        ))
+    }
+
+    #[test]
+    fn saves_load_back_the_same() {
+        let path = temp_path("round-trip.json");
+        let mut goblin = Creature::new("Goblin");
+        goblin.health = 6;
+        goblin.initiative = Some(14);
+        let mut encounter = Encounter::new(vec![Creature::new("Ezren"), goblin]);
+        encounter.round = 3;
+        let save = SaveFile {
+            version: SAVE_VERSION,
+            selected_creature: Some(1),
+            encounter,
+        };
+
+        save.write(&path).unwrap();
+        let loaded = SaveFile::load(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded.version, SAVE_VERSION);
+        assert_eq!(loaded.selected_creature, Some(1));
+        assert_eq!(loaded.encounter, save.encounter);
+    }
+
+    #[test]
+    fn newer_versions_are_rejected() {
+        let path = temp_path("newer.json");
+        let save = SaveFile {
+            version: SAVE_VERSION + 1,
+            selected_creature: None,
+            encounter: Encounter::default(),
+        };
+
+        save.write(&path).unwrap();
+        let loaded = SaveFile::load(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        let error = loaded.unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains("only understands up to"));
+    }
+
+    #[test]
+    fn broken_files_are_errors() {
+        let path = temp_path("broken.json");
+        std::fs::write(&path, "{\"version\": 1, \"creatures\": [").unwrap();
+        let loaded = SaveFile::load(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(loaded.is_err());
+        assert!(SaveFile::load(&temp_path("missing.json")).is_err());
+    }
+}
@@ -0,0 +1,155 @@
+use std::env;
+
+use clap::ValueEnum;
+use combat_tracker::Side;
+use ratatui::style::{Color, Modifier, Style};
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum ThemeName {
+    #[default]
+    Dark,
+    Light,
+    // Bright colors only, for projectors and bad eyes
+    HighContrast,
+    NoColor,
+}
+
+impl ThemeName {
+    /// What to use when neither the command line nor the config picked a theme,
+    /// see https://no-color.org
+    pub fn from_env() -> Self {
+        match env::var_os("NO_COLOR") {
+            Some(value) if !value.is_empty() => ThemeName::NoColor,
+            _ => ThemeName::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    // Creature rows
+    pub text: Style,
+    pub selected: Style,
+    // Patched over the row of the creature whose turn it is
+    pub active: Style,
+    // Instruction banners and help
+    pub label: Style,
+    pub key: Style,
+    // Focused borders and highlighted choices
+    pub accent: Style,
+    // Things that are there but not in use, like conditions a creature doesn't have
+    pub muted: Style,
+    pub line_numbers: Style,
+    // Health gauge above half, above a quarter and below that
+    pub healthy: Style,
+    pub bloodied: Style,
+    pub critical: Style,
+    // Creature names by side
+    pub enemy: Style,
+    pub party: Style,
+    pub ally: Style,
+    pub neutral: Style,
+}
+
+impl Theme {
+    pub fn named(name: ThemeName) -> Self {
+        match name {
+            ThemeName::Dark => Theme {
+                text: Style::new().fg(Color::White).bg(Color::Black),
+                selected: Style::new().fg(Color::Black).bg(Color::White),
+                active: Style::new().fg(Color::Yellow),
+                label: Style::new().fg(Color::White),
+                key: Style::new().fg(Color::Blue).add_modifier(Modifier::BOLD),
+                accent: Style::new().fg(Color::Blue),
+                muted: Style::new().fg(Color::DarkGray),
+                line_numbers: Style::new().bg(Color::DarkGray),
+                healthy: Style::new().fg(Color::Green),
+                bloodied: Style::new().fg(Color::Yellow),
+                critical: Style::new().fg(Color::Red),
+                enemy: Style::new().fg(Color::LightRed),
+                party: Style::new().fg(Color::LightCyan),
+                ally: Style::new().fg(Color::LightGreen),
+                neutral: Style::new().fg(Color::Gray),
+            },
+            ThemeName::Light => Theme {
+                text: Style::new().fg(Color::Black).bg(Color::White),
+                selected: Style::new().fg(Color::White).bg(Color::Blue),
+                active: Style::new().fg(Color::Magenta),
+                label: Style::new().fg(Color::Black),
+                key: Style::new().fg(Color::Blue).add_modifier(Modifier::BOLD),
+                accent: Style::new().fg(Color::Blue),
+                muted: Style::new().fg(Color::Gray),
+                line_numbers: Style::new().fg(Color::Black).bg(Color::Gray),
+                healthy: Style::new().fg(Color::Green),
+                // Yellow disappears on white
+                bloodied: Style::new().fg(Color::Indexed(172)),
+                critical: Style::new().fg(Color::Red),
+                enemy: Style::new().fg(Color::Red),
+                party: Style::new().fg(Color::Blue),
+                ally: Style::new().fg(Color::Green),
+                neutral: Style::new().fg(Color::DarkGray),
+            },
+            ThemeName::HighContrast => Theme {
+                text: Style::new().fg(Color::White).bg(Color::Black),
+                selected: Style::new()
+                    .fg(Color::Black)
+                    .bg(Color::LightYellow)
+                    .add_modifier(Modifier::BOLD),
+                active: Style::new()
+                    .fg(Color::LightCyan)
+                    .add_modifier(Modifier::UNDERLINED),
+                label: Style::new().fg(Color::White),
+                key: Style::new()
+                    .fg(Color::LightYellow)
+                    .add_modifier(Modifier::BOLD),
+                accent: Style::new().fg(Color::LightCyan),
+                muted: Style::new().fg(Color::White),
+                line_numbers: Style::new().fg(Color::Black).bg(Color::White),
+                healthy: Style::new().fg(Color::LightGreen),
+                bloodied: Style::new().fg(Color::LightYellow),
+                critical: Style::new().fg(Color::LightRed),
+                enemy: Style::new().fg(Color::LightRed),
+                party: Style::new().fg(Color::LightCyan),
+                ally: Style::new().fg(Color::LightGreen),
+                neutral: Style::new().fg(Color::White),
+            },
+            // Only modifiers, the terminal's own colors do the rest
+            ThemeName::NoColor => Theme {
+                text: Style::new(),
+                selected: Style::new().add_modifier(Modifier::REVERSED),
+                active: Style::new(),
+                label: Style::new(),
+                key: Style::new().add_modifier(Modifier::BOLD),
+                accent: Style::new().add_modifier(Modifier::BOLD),
+                muted: Style::new().add_modifier(Modifier::DIM),
+                line_numbers: Style::new().add_modifier(Modifier::DIM),
+                healthy: Style::new(),
+                bloodied: Style::new(),
+                critical: Style::new(),
+                // The name tags tell the sides apart
+                enemy: Style::new(),
+                party: Style::new(),
+                ally: Style::new(),
+                neutral: Style::new(),
+            },
+        }
+    }
+
+    /// Style of a creature's name
+    pub fn side(&self, side: Side) -> Style {
+        match side {
+            Side::Enemy => self.enemy,
+            Side::Party => self.party,
+            Side::Ally => self.ally,
+            Side::Neutral => self.neutral,
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::named(ThemeName::default())
+    }
+}